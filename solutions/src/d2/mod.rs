@@ -1,7 +1,4 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-};
+use std::{fs::read, io::BufRead};
 
 // A X  rock
 // B Y  paper
@@ -12,13 +9,14 @@ use std::{
 // defeat   0
 //
 
+#[derive(Clone, Copy)]
 enum GameResult {
     Win,
     Defeat,
     Draw,
 }
 impl GameResult {
-    fn get_points(&self) -> u32 {
+    const fn get_points(&self) -> u32 {
         match self {
             Self::Win => 6,
             Self::Draw => 3,
@@ -35,7 +33,7 @@ impl GameResult {
             _ => None,
         }
     }
-    fn get_move_for_result(&self, mv: MoveOption) -> MoveOption {
+    const fn get_move_for_result(&self, mv: MoveOption) -> MoveOption {
         match self {
             Self::Win => match mv {
                 MoveOption::Rock => MoveOption::Paper,
@@ -52,6 +50,7 @@ impl GameResult {
     }
 }
 
+#[derive(Clone, Copy)]
 enum MoveOption {
     Rock,
     Paper,
@@ -66,7 +65,7 @@ impl MoveOption {
             _ => None,
         }
     }
-    fn get_result(&self, other: &Self) -> GameResult {
+    const fn get_result(&self, other: &Self) -> GameResult {
         match self {
             Self::Rock => match other {
                 Self::Rock => GameResult::Draw,
@@ -85,7 +84,7 @@ impl MoveOption {
             },
         }
     }
-    fn get_move_points(&self) -> u32 {
+    const fn get_move_points(&self) -> u32 {
         match self {
             Self::Rock => 1,
             Self::Paper => 2,
@@ -94,10 +93,118 @@ impl MoveOption {
     }
 }
 
+const MOVES: [MoveOption; 3] = [MoveOption::Rock, MoveOption::Paper, MoveOption::Scissors];
+const RESULTS: [GameResult; 3] = [GameResult::Defeat, GameResult::Draw, GameResult::Win];
+
+// Score of every possible line, indexed by `3 * (opponent - 'A') + (own - 'X')`.
+pub type ScoreTable = [u32; 9];
+
+pub const FIRST_PART_TABLE: ScoreTable = build_first_part_table();
+pub const SEC_PART_TABLE: ScoreTable = build_sec_part_table();
+
+const fn build_first_part_table() -> ScoreTable {
+    let mut table = [0; 9];
+    let mut idx = 0;
+    while idx < 9 {
+        let (theirs, ours) = (&MOVES[idx / 3], &MOVES[idx % 3]);
+        table[idx] = ours.get_result(theirs).get_points() + ours.get_move_points();
+        idx += 1;
+    }
+
+    table
+}
+
+const fn build_sec_part_table() -> ScoreTable {
+    let mut table = [0; 9];
+    let mut idx = 0;
+    while idx < 9 {
+        let (theirs, result) = (MOVES[idx / 3], &RESULTS[idx % 3]);
+        table[idx] = result.get_move_for_result(theirs).get_move_points() + result.get_points();
+        idx += 1;
+    }
+
+    table
+}
+
+// Scores a strategy guide of `"A X"` lines straight from its bytes.
+// Well-formed input (every line is exactly 3 bytes plus `\n`) goes through a
+// branch-free loop over 4-byte chunks; anything else is rescored line by line
+// so a malformed line can be reported with its number.
+pub fn score_guide(input: &[u8], table: &ScoreTable) -> Result<u32, String> {
+    let (chunks, tail) = input.split_at(input.len() - input.len() % 4);
+    let score_line = |line: &[u8]| {
+        let theirs = line[0].wrapping_sub(b'A');
+        let ours = line[2].wrapping_sub(b'X');
+        let idx = usize::from(theirs.min(2)) * 3 + usize::from(ours.min(2));
+
+        (table[idx], theirs < 3 && ours < 3 && line[1] == b' ')
+    };
+
+    let (mut total, mut valid) = chunks
+        .chunks_exact(4)
+        .map(|chunk| {
+            let (points, ok) = score_line(chunk);
+            (points, ok & (chunk[3] == b'\n'))
+        })
+        .fold((0u32, true), |(sum, valid), (points, ok)| {
+            (sum + points, valid & ok)
+        });
+    match tail.len() {
+        0 => (),
+        3 => {
+            let (points, ok) = score_line(tail);
+            total += points;
+            valid &= ok;
+        }
+        _ => valid = false,
+    }
+
+    if valid {
+        Ok(total)
+    } else {
+        score_guide_by_lines(input, table)
+    }
+}
+
+fn score_guide_by_lines(input: &[u8], table: &ScoreTable) -> Result<u32, String> {
+    let mut total = 0;
+    for (line_idx, line) in input.split(|b| *b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        match line {
+            [theirs @ b'A'..=b'C', b' ', ours @ b'X'..=b'Z'] => {
+                total += table[usize::from(theirs - b'A') * 3 + usize::from(ours - b'X')];
+            }
+            _ => {
+                return Err(format!(
+                    "Malformed line {}: {:?}",
+                    line_idx + 1,
+                    String::from_utf8_lossy(line)
+                ))
+            }
+        }
+    }
+
+    Ok(total)
+}
+
 pub fn do_first_part(file_path: &str) -> u32 {
-    let f = File::open(file_path).expect("failed to open file");
+    let content = read(file_path).expect("failed to open file");
 
-    BufReader::new(f)
+    score_guide(&content, &FIRST_PART_TABLE).unwrap()
+}
+
+pub fn do_sec_part(file_path: &str) -> u32 {
+    let content = read(file_path).expect("failed to open file");
+
+    score_guide(&content, &SEC_PART_TABLE).unwrap()
+}
+
+// Enum-based scoring the lookup tables are derived from, kept as a reference.
+pub fn first_part_by_moves<R: BufRead>(reader: R) -> u32 {
+    reader
         .lines()
         .filter_map(|l| l.ok())
         .map(|l| {
@@ -112,10 +219,8 @@ pub fn do_first_part(file_path: &str) -> u32 {
         .fold(0, |acc, r| acc + r)
 }
 
-pub fn do_sec_part(file_path: &str) -> u32 {
-    let f = File::open(file_path).expect("failed to open file");
-
-    BufReader::new(f)
+pub fn sec_part_by_moves<R: BufRead>(reader: R) -> u32 {
+    reader
         .lines()
         .filter_map(|l| l.ok())
         .map(|l| {
@@ -134,6 +239,7 @@ pub fn do_sec_part(file_path: &str) -> u32 {
 #[cfg(test)]
 mod d2_tests {
     use super::*;
+    use crate::test_rng::TestRng;
 
     #[test]
     fn test_first() {
@@ -148,4 +254,64 @@ mod d2_tests {
 
         assert_eq!(res, 12);
     }
+
+    fn generate_guide(lines: usize) -> Vec<u8> {
+        let mut rng = TestRng::new(0x2545_f491);
+        let mut guide = Vec::with_capacity(lines * 4);
+        for _ in 0..lines {
+            guide.extend_from_slice(&[
+                b'A' + rng.below(3) as u8,
+                b' ',
+                b'X' + rng.below(3) as u8,
+                b'\n',
+            ]);
+        }
+
+        guide
+    }
+
+    #[test]
+    fn test_table_matches_moves() {
+        let guide = generate_guide(10_000);
+
+        assert_eq!(
+            score_guide(&guide, &FIRST_PART_TABLE),
+            Ok(first_part_by_moves(&guide[..]))
+        );
+        assert_eq!(
+            score_guide(&guide, &SEC_PART_TABLE),
+            Ok(sec_part_by_moves(&guide[..]))
+        );
+    }
+
+    #[test]
+    fn test_irregular_lines() {
+        assert_eq!(
+            score_guide(b"A Y\r\nB X\r\n\nC Z", &FIRST_PART_TABLE),
+            Ok(15)
+        );
+        assert_eq!(score_guide(b"A Y\nB X\nC Z", &SEC_PART_TABLE), Ok(12));
+        assert!(score_guide(b"A Y\nB W\n", &FIRST_PART_TABLE).is_err());
+        assert!(score_guide(b"A Y\nB  X\n", &FIRST_PART_TABLE).is_err());
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --release -- --ignored --nocapture"]
+    fn bench_table_vs_moves() {
+        use std::time::Instant;
+
+        let guide = generate_guide(5_000_000);
+
+        let start = Instant::now();
+        let by_table = score_guide(&guide, &FIRST_PART_TABLE).unwrap();
+        let table_time = start.elapsed();
+
+        let start = Instant::now();
+        let by_moves = first_part_by_moves(&guide[..]);
+        let moves_time = start.elapsed();
+
+        println!("table: {table_time:?}, moves: {moves_time:?}");
+        assert_eq!(by_table, by_moves);
+        assert!(table_time < moves_time);
+    }
 }
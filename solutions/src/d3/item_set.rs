@@ -0,0 +1,71 @@
+use super::get_char_cost;
+
+// Set of rucksack item types packed into a u64, bit `p` is set when the item
+// with priority `p` (1..=52) is present.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ItemSet(u64);
+
+impl ItemSet {
+    pub fn new() -> Self {
+        Self(0)
+    }
+    pub fn from_items(items: &str) -> Result<Self, String> {
+        items.chars().try_fold(Self::new(), |mut set, ch| {
+            set.insert(get_char_cost(ch)?)?;
+            Ok(set)
+        })
+    }
+    pub fn insert(&mut self, priority: u32) -> Result<(), String> {
+        if !(1..=52).contains(&priority) {
+            return Err(format!("Priority {priority} is not in 1..=52"));
+        }
+        self.0 |= 1 << priority;
+
+        Ok(())
+    }
+    pub fn contains(&self, priority: u32) -> bool {
+        priority < 64 && self.0 & (1 << priority) != 0
+    }
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self(self.0 | other.0)
+    }
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self(self.0 & other.0)
+    }
+    pub fn difference(&self, other: &Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+    // Items present in every set, empty when there are no sets at all.
+    pub fn intersect_all<I: IntoIterator<Item = Self>>(sets: I) -> Self {
+        let mut sets = sets.into_iter();
+
+        sets.next()
+            .map(|first| sets.fold(first, |acc, s| acc.intersection(&s)))
+            .unwrap_or_default()
+    }
+
+    // Priorities of the contained items in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> {
+        let mut bits = self.0;
+
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let priority = bits.trailing_zeros();
+            bits &= bits - 1;
+
+            Some(priority)
+        })
+    }
+    pub fn priority_sum(&self) -> u32 {
+        self.iter().sum()
+    }
+}
@@ -1,53 +1,199 @@
 use std::{
-    collections::HashSet,
     fs::File,
     io::{BufRead, BufReader},
 };
 
-fn get_char_cost(ch: char) -> u32 {
-    u32::from(ch) - if ch.is_uppercase() { 38 } else { 96 }
+mod item_set;
+mod planner;
+
+pub use item_set::ItemSet;
+pub use planner::{format_plans, plan_reorganisation, plan_rucksack, ItemMove, RucksackPlan};
+
+fn get_char_cost(ch: char) -> Result<u32, String> {
+    match ch {
+        'a'..='z' => Ok(u32::from(ch) - 96),
+        'A'..='Z' => Ok(u32::from(ch) - 38),
+        _ => Err(format!("Unknown item {ch:?}")),
+    }
 }
 
-pub fn do_first_part(file_path: &str) -> u32 {
+fn get_cost_char(priority: u32) -> char {
+    char::from_u32(priority + if priority > 26 { 38 } else { 96 }).unwrap()
+}
+
+fn read_lines(file_path: &str) -> Vec<String> {
     let f = File::open(file_path).expect("Failed to open file");
 
-    BufReader::new(f)
-        .lines()
-        .filter_map(|l| l.ok())
-        .filter_map(|l| {
-            let (first_half, sec_half) = (&l[..l.len() / 2], &l[l.len() / 2..]);
-            let mut chars_dict = HashSet::new();
+    BufReader::new(f).lines().map_while(Result::ok).collect()
+}
 
-            for c in first_half.chars() {
-                chars_dict.insert(c);
-            }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RucksackConfig {
+    pub compartments: usize,
+    pub group_size: usize,
+}
+impl Default for RucksackConfig {
+    fn default() -> Self {
+        Self {
+            compartments: 2,
+            group_size: 3,
+        }
+    }
+}
 
-            sec_half.chars().find(|c| chars_dict.contains(c))
-        })
-        .fold(0, |acc, ch| acc + get_char_cost(ch))
+pub fn split_compartments(rucksack: &str, compartments: usize) -> Result<Vec<&str>, String> {
+    if compartments == 0 {
+        return Err("Rucksack must have at least one compartment".to_string());
+    }
+    if !rucksack.is_ascii() || !rucksack.len().is_multiple_of(compartments) {
+        return Err(format!(
+            "Rucksack {rucksack:?} can't be split into {compartments} equal compartments"
+        ));
+    }
+
+    // An empty rucksack has nothing in any of its compartments.
+    if rucksack.is_empty() {
+        return Ok(vec![""; compartments]);
+    }
+
+    Ok(rucksack
+        .as_bytes()
+        .chunks(rucksack.len() / compartments)
+        .map(|c| std::str::from_utf8(c).unwrap())
+        .collect())
 }
 
-pub fn do_sec_part(file_path: &str) -> u32 {
-    let f = File::open(file_path).expect("Failed to open file");
+// Items found in more than one compartment of the rucksack.
+pub fn misplaced_items(rucksack: &str, compartments: usize) -> Result<ItemSet, String> {
+    let mut seen = ItemSet::new();
+    let mut shared = ItemSet::new();
+    for compartment in split_compartments(rucksack, compartments)? {
+        let items = ItemSet::from_items(compartment)?;
+        shared = shared.union(&seen.intersection(&items));
+        seen = seen.union(&items);
+    }
+
+    Ok(shared)
+}
 
-    let r = BufReader::new(f)
-        .lines()
-        .filter_map(|l| l.ok())
-        .collect::<Vec<_>>();
+pub fn misplaced_priority(rucksack: &str, compartments: usize) -> Result<u32, String> {
+    misplaced_items(rucksack, compartments).map(|shared| shared.priority_sum())
+}
+
+// Items carried by every rucksack of each group of `group_size` lines.
+pub fn group_badges(rucksacks: &[String], group_size: usize) -> Result<Vec<ItemSet>, String> {
+    if group_size == 0 {
+        return Err("Group must have at least one rucksack".to_string());
+    }
+    if !rucksacks.len().is_multiple_of(group_size) {
+        return Err(format!(
+            "Last group has {} rucksacks instead of {group_size}",
+            rucksacks.len() % group_size
+        ));
+    }
 
-    r[..]
-        .chunks(3)
-        .map(|chunk| {
-            chunk
+    rucksacks
+        .chunks(group_size)
+        .map(|group| {
+            let sets = group
                 .iter()
-                .map(|c| {
-                    c.chars()
-                        .map(|c| get_char_cost(c))
-                        .fold(0u64, |acc, c| acc | (1 << c - 1))
-                })
-                .fold(u64::MAX, |acc, c| acc & c)
+                .map(|r| ItemSet::from_items(r))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(ItemSet::intersect_all(sets))
         })
-        .fold(0, |acc, v| acc + v.trailing_zeros() + 1)
+        .collect()
+}
+
+pub fn badges_priority(rucksacks: &[String], group_size: usize) -> Result<u32, String> {
+    group_badges(rucksacks, group_size).map(|badges| badges.iter().map(|b| b.priority_sum()).sum())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Report {
+    pub config: RucksackConfig,
+    pub misplaced: Vec<ItemSet>,
+    pub badges: Vec<ItemSet>,
+}
+impl Report {
+    pub fn new(rucksacks: &[String], config: RucksackConfig) -> Result<Self, String> {
+        let misplaced = rucksacks
+            .iter()
+            .enumerate()
+            .map(|(idx, r)| {
+                misplaced_items(r, config.compartments)
+                    .map_err(|e| format!("Line {}: {e}", idx + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let badges = group_badges(rucksacks, config.group_size)?;
+
+        Ok(Self {
+            config,
+            misplaced,
+            badges,
+        })
+    }
+    pub fn misplaced_priority(&self) -> u32 {
+        self.misplaced.iter().map(|s| s.priority_sum()).sum()
+    }
+    pub fn badges_priority(&self) -> u32 {
+        self.badges.iter().map(|s| s.priority_sum()).sum()
+    }
+}
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items = |set: &ItemSet| set.iter().map(get_cost_char).collect::<String>();
+
+        for (idx, shared) in self.misplaced.iter().enumerate() {
+            writeln!(
+                f,
+                "rucksack {}: {} ({})",
+                idx + 1,
+                items(shared),
+                shared.priority_sum()
+            )?;
+        }
+        for (idx, badges) in self.badges.iter().enumerate() {
+            let first_line = idx * self.config.group_size + 1;
+            writeln!(
+                f,
+                "group {} (lines {}-{}): {} ({})",
+                idx + 1,
+                first_line,
+                first_line + self.config.group_size - 1,
+                items(badges),
+                badges.priority_sum()
+            )?;
+        }
+        write!(
+            f,
+            "total: misplaced {}, badges {}",
+            self.misplaced_priority(),
+            self.badges_priority()
+        )
+    }
+}
+
+pub fn report(file_path: &str, config: RucksackConfig) -> Result<Report, String> {
+    Report::new(&read_lines(file_path), config)
+}
+
+pub fn plan(file_path: &str) -> Result<Vec<RucksackPlan>, String> {
+    plan_reorganisation(&read_lines(file_path))
+}
+
+pub fn do_first_part(file_path: &str) -> u32 {
+    let compartments = RucksackConfig::default().compartments;
+
+    read_lines(file_path)
+        .iter()
+        .map(|l| misplaced_priority(l, compartments))
+        .sum::<Result<u32, String>>()
+        .unwrap()
+}
+
+pub fn do_sec_part(file_path: &str) -> u32 {
+    badges_priority(&read_lines(file_path), RucksackConfig::default().group_size).unwrap()
 }
 
 #[cfg(test)]
@@ -67,4 +213,87 @@ mod d3_test {
 
         assert_eq!(res, 70);
     }
+
+    #[test]
+    fn test_item_set() {
+        let a = ItemSet::from_items("abcZ").unwrap();
+        let b = ItemSet::from_items("cZzz").unwrap();
+
+        assert_eq!(a.intersection(&b).iter().collect::<Vec<_>>(), vec![3, 52]);
+        assert_eq!(a.union(&b).len(), 5);
+        assert_eq!(a.difference(&b).priority_sum(), 3);
+        assert!(b.contains(26) && !b.contains(1));
+        assert!(ItemSet::intersect_all([]).is_empty());
+        assert!(ItemSet::from_items("ab1").is_err());
+        let mut set = ItemSet::new();
+        assert!(set.insert(52).is_ok() && set.contains(52));
+        assert!(set.insert(0).is_err());
+        assert!(set.insert(53).is_err());
+        assert!(set.insert(64).is_err());
+        assert_eq!(set.len(), 1);
+        assert!(misplaced_priority("aé", 2).is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let rucksacks = ["abcaBa", "xyzabc", "cab"].map(String::from);
+        let config = RucksackConfig {
+            compartments: 3,
+            group_size: 3,
+        };
+        let res = Report::new(&rucksacks, config).unwrap();
+
+        assert_eq!(res.misplaced.len(), 3);
+        assert_eq!(res.misplaced[0].priority_sum(), 1);
+        assert_eq!(res.misplaced[1].priority_sum(), 0);
+        assert_eq!(res.badges_priority(), 6);
+        assert!(res.to_string().starts_with("rucksack 1: a (1)\n"));
+        assert!(res.to_string().contains("group 1 (lines 1-3): abc (6)\n"));
+
+        assert!(Report::new(&rucksacks, RucksackConfig::default()).is_err());
+        assert!(group_badges(&rucksacks[..2], 3).is_err());
+        assert!(misplaced_items("abc", 2).is_err());
+        assert!(misplaced_items("abc", 0).is_err());
+
+        let with_blank = ["abcaBa", "", "cab"].map(String::from);
+        let res = Report::new(&with_blank, config).unwrap();
+        assert!(res.misplaced[1].is_empty());
+        assert!(res.badges[0].is_empty());
+        assert_eq!(split_compartments("", 2).unwrap(), vec!["", ""]);
+        assert_eq!(misplaced_priority("", 2), Ok(0));
+        assert!(plan_rucksack("").unwrap().moves.is_empty());
+    }
+
+    #[test]
+    fn test_plan() {
+        let plans = plan("./src/d3/test.txt").unwrap();
+
+        let min_moves = [2, 4, 2, 4, 4, 2];
+        for (plan, moves) in plans.iter().zip(min_moves) {
+            assert_eq!(misplaced_priority(&plan.rearranged, 2), Ok(0));
+            assert_eq!(plan.moves.len(), moves);
+        }
+        assert_eq!(format_plans(&plans).lines().count(), 6);
+
+        let res = plan_rucksack("aabzAa").unwrap();
+        assert_eq!(res.rearranged, "aaabzA");
+        assert_eq!(
+            res.moves,
+            vec![
+                ItemMove {
+                    item: 'b',
+                    from: 0,
+                    to: 1
+                },
+                ItemMove {
+                    item: 'a',
+                    from: 1,
+                    to: 0
+                },
+            ]
+        );
+        assert_eq!(res.moved_priority(), 3);
+        assert_eq!(plan_rucksack("abcd").unwrap().moves, vec![]);
+        assert!(plan_rucksack("aaab").is_err());
+    }
 }
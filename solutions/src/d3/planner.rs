@@ -0,0 +1,124 @@
+use super::{get_char_cost, split_compartments, ItemSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemMove {
+    pub item: char,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RucksackPlan {
+    pub moves: Vec<ItemMove>,
+    pub rearranged: String,
+}
+impl RucksackPlan {
+    pub fn moved_priority(&self) -> u32 {
+        self.moves
+            .iter()
+            .map(|m| get_char_cost(m.item).unwrap())
+            .sum()
+    }
+}
+
+// Cost of a partial assignment, compared by number of moved items first and
+// by the priority of moved items second.
+type Cost = (usize, u32);
+
+// Every item type has to end up in a single compartment, so the plan picks a
+// side per type. Keeping the compartments equally sized turns this into a
+// knapsack over item types: `best[i][s]` is the cheapest way to place the
+// first `i` types with `s` items in the first compartment.
+pub fn plan_rucksack(rucksack: &str) -> Result<RucksackPlan, String> {
+    let compartments = split_compartments(rucksack, 2)?;
+    let (first, sec) = (compartments[0], compartments[1]);
+
+    let mut counts = [[0usize; 2]; 53];
+    for (side, compartment) in [first, sec].into_iter().enumerate() {
+        for ch in compartment.chars() {
+            counts[get_char_cost(ch)? as usize][side] += 1;
+        }
+    }
+    let kinds = ItemSet::from_items(rucksack)?.iter().collect::<Vec<_>>();
+    let capacity = first.len();
+
+    let mut best: Vec<Vec<Option<Cost>>> = vec![vec![None; capacity + 1]; kinds.len() + 1];
+    best[0][0] = Some((0, 0));
+    for (idx, &kind) in kinds.iter().enumerate() {
+        let [in_first, in_sec] = counts[kind as usize];
+        for size in 0..=capacity {
+            let Some((moves, priority)) = best[idx][size] else {
+                continue;
+            };
+            let options = [(size + in_first + in_sec, in_sec), (size, in_first)];
+            for (new_size, moved) in options {
+                if new_size > capacity {
+                    continue;
+                }
+                let cost = (moves + moved, priority + kind * moved as u32);
+                let slot = &mut best[idx + 1][new_size];
+                if slot.is_none_or(|c| cost < c) {
+                    *slot = Some(cost);
+                }
+            }
+        }
+    }
+    if best[kinds.len()][capacity].is_none() {
+        return Err(format!(
+            "Rucksack {rucksack:?} can't be split into compartments without shared items"
+        ));
+    }
+
+    let mut keep_first = ItemSet::new();
+    let mut size = capacity;
+    for (idx, &kind) in kinds.iter().enumerate().rev() {
+        let [in_first, in_sec] = counts[kind as usize];
+        let kept = size
+            .checked_sub(in_first + in_sec)
+            .and_then(|prev_size| best[idx][prev_size])
+            .map(|(moves, priority)| (moves + in_sec, priority + kind * in_sec as u32));
+        if kept == best[idx + 1][size] {
+            // Kinds come from an ItemSet, so they are valid priorities.
+            keep_first.insert(kind).unwrap();
+            size -= in_first + in_sec;
+        }
+    }
+
+    let target_of = |ch: char| usize::from(!keep_first.contains(get_char_cost(ch).unwrap()));
+    let mut moves = Vec::new();
+    let mut rearranged = [String::new(), String::new()];
+    for (side, compartment) in [first, sec].into_iter().enumerate() {
+        for ch in compartment.chars() {
+            let target = target_of(ch);
+            if target != side {
+                moves.push(ItemMove {
+                    item: ch,
+                    from: side,
+                    to: target,
+                });
+            }
+            rearranged[target].push(ch);
+        }
+    }
+
+    Ok(RucksackPlan {
+        moves,
+        rearranged: rearranged.concat(),
+    })
+}
+
+pub fn plan_reorganisation(rucksacks: &[String]) -> Result<Vec<RucksackPlan>, String> {
+    rucksacks
+        .iter()
+        .enumerate()
+        .map(|(idx, r)| plan_rucksack(r).map_err(|e| format!("Line {}: {e}", idx + 1)))
+        .collect()
+}
+
+pub fn format_plans(plans: &[RucksackPlan]) -> String {
+    plans
+        .iter()
+        .map(|p| p.rearranged.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,244 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    ops::{Add, Sub},
+    str::FromStr,
+};
+
+// Values an interval can be built over: ordered and discrete, stepping by one
+// comes from `From<u8>`. Intervals may reach the largest value of the type
+// and distances between signed values may not fit, so those use the checked
+// operations.
+pub trait Bound: Copy + Ord + Add<Output = Self> + Sub<Output = Self> + From<u8> {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_succ(self) -> Option<Self> {
+        self.checked_add(Self::from(1))
+    }
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(impl Bound for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+        })*
+    };
+}
+impl_bound!(u8, u16, u32, u64, u128, usize, i16, i32, i64, i128, isize);
+
+// Closed interval `start..=end` over a discrete domain such as section ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Bound> Interval<T> {
+    pub fn new(start: T, end: T) -> Result<Self, String> {
+        if start > end {
+            return Err("Interval start is greater than its end".to_string());
+        }
+
+        Ok(Self { start, end })
+    }
+    pub fn point(p: T) -> Self {
+        Self { start: p, end: p }
+    }
+    pub fn start(&self) -> T {
+        self.start
+    }
+    pub fn end(&self) -> T {
+        self.end
+    }
+    // Number of points, `None` when it doesn't fit in `T`.
+    pub fn len(&self) -> Option<T> {
+        self.end.checked_sub(self.start)?.checked_succ()
+    }
+
+    pub fn contains_point(&self, p: T) -> bool {
+        p >= self.start && p <= self.end
+    }
+    pub fn contains(&self, other: &Self) -> bool {
+        self.contains_point(other.start) && self.contains_point(other.end)
+    }
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+    // True when the intervals overlap or one starts right after the other ends.
+    pub fn touches(&self, other: &Self) -> bool {
+        self.overlaps(other)
+            || self.end.checked_succ() == Some(other.start)
+            || other.end.checked_succ() == Some(self.start)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        self.overlaps(other).then(|| Self {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+        })
+    }
+    // Single interval covering both, only exists when they touch.
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        self.touches(other).then(|| Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        })
+    }
+    // Parts of `self` not covered by `other`, at most two of them.
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        let Some(common) = self.intersection(other) else {
+            return vec![*self];
+        };
+        let mut res = Vec::with_capacity(2);
+        if self.start < common.start {
+            res.push(Self {
+                start: self.start,
+                end: common.start - T::from(1),
+            });
+        }
+        if common.end < self.end {
+            res.push(Self {
+                start: common.end + T::from(1),
+                end: self.end,
+            });
+        }
+
+        res
+    }
+}
+
+impl<T> FromStr for Interval<T>
+where
+    T: Bound + FromStr,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("Interval {s:?} is not in `start-end` form"))?;
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<T>()
+                .map_err(|_| format!("Failed to parse {v:?} in interval {s:?}"))
+        };
+
+        Self::new(parse(start)?, parse(end)?)
+    }
+}
+
+impl<T: Display> Display for Interval<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+// Union of intervals kept as a sorted list of disjoint, non-touching ranges.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Bound> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self {
+            intervals: Vec::new(),
+        }
+    }
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let first = self
+            .intervals
+            .partition_point(|i| i.end < interval.start && !i.touches(&interval));
+        let mut merged = interval;
+        let mut last = first;
+        while last < self.intervals.len() && self.intervals[last].touches(&merged) {
+            merged = merged.union(&self.intervals[last]).unwrap();
+            last += 1;
+        }
+
+        self.intervals.splice(first..last, [merged]);
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.intervals.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+    pub fn contains_point(&self, p: T) -> bool {
+        let idx = self.intervals.partition_point(|i| i.end < p);
+
+        self.intervals.get(idx).is_some_and(|i| i.contains_point(p))
+    }
+    // Uncovered ranges between consecutive intervals of the set.
+    pub fn gaps(&self) -> Vec<Interval<T>> {
+        self.intervals
+            .windows(2)
+            .map(|w| Interval {
+                start: w[0].end + T::from(1),
+                end: w[1].start - T::from(1),
+            })
+            .collect()
+    }
+    // Number of distinct points covered by the set, `None` when it doesn't
+    // fit in `T`.
+    pub fn coverage(&self) -> Option<T> {
+        self.intervals
+            .iter()
+            .try_fold(T::from(0), |acc, i| acc.checked_add(i.len()?))
+    }
+}
+
+// Splits the covered part of the line into maximal runs where the same number
+// of intervals overlap, returned in ascending order with that number.
+pub fn depth_runs<T: Bound>(intervals: &[Interval<T>]) -> Vec<(Interval<T>, usize)> {
+    let mut deltas: BTreeMap<T, isize> = BTreeMap::new();
+    for i in intervals {
+        *deltas.entry(i.start).or_default() += 1;
+        // Intervals ending at the largest value of `T` never close.
+        if let Some(after) = i.end.checked_succ() {
+            *deltas.entry(after).or_default() -= 1;
+        }
+    }
+    let last_end = intervals.iter().map(|i| i.end).max();
+
+    let mut depth = 0isize;
+    let mut runs: Vec<(Interval<T>, usize)> = Vec::new();
+    let mut points = deltas.into_iter().peekable();
+    while let Some((point, delta)) = points.next() {
+        depth += delta;
+        let end = match (points.peek(), last_end) {
+            (Some((next, _)), _) => *next - T::from(1),
+            (None, Some(last_end)) if depth > 0 => last_end,
+            _ => break,
+        };
+        let run = Interval { start: point, end };
+        match runs.last_mut() {
+            Some((last, d)) if *d == depth as usize && last.end.checked_succ() == Some(point) => {
+                last.end = run.end;
+            }
+            _ if depth > 0 => runs.push((run, depth as usize)),
+            _ => (),
+        }
+    }
+
+    runs
+}
+
+impl<T: Bound> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+
+        set
+    }
+}
@@ -0,0 +1,123 @@
+use super::interval::{Bound, Interval};
+
+// Static interval tree: intervals are sorted by start and laid out as an
+// implicit balanced tree (the middle of every range is its root), with each
+// root remembering the largest end inside its subtree. Ids are the positions
+// of the intervals in the slice the tree was built from.
+#[derive(Debug)]
+pub struct IntervalTree<T> {
+    items: Vec<(Interval<T>, usize)>,
+    max_end: Vec<T>,
+    positions: Vec<usize>,
+}
+
+impl<T: Bound> IntervalTree<T> {
+    pub fn new(intervals: &[Interval<T>]) -> Self {
+        let mut items = intervals
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(id, i)| (i, id))
+            .collect::<Vec<_>>();
+        items.sort_unstable();
+
+        let mut max_end = items.iter().map(|(i, _)| i.end()).collect::<Vec<_>>();
+        Self::fill_max_end(&items, &mut max_end, 0, items.len());
+        let mut positions = vec![0; items.len()];
+        for (pos, (_, id)) in items.iter().enumerate() {
+            positions[*id] = pos;
+        }
+
+        Self {
+            items,
+            max_end,
+            positions,
+        }
+    }
+    fn fill_max_end(
+        items: &[(Interval<T>, usize)],
+        max_end: &mut [T],
+        lo: usize,
+        hi: usize,
+    ) -> Option<T> {
+        if lo >= hi {
+            return None;
+        }
+        let mid = (lo + hi) / 2;
+        let left = Self::fill_max_end(items, max_end, lo, mid);
+        let right = Self::fill_max_end(items, max_end, mid + 1, hi);
+        max_end[mid] = [left, right]
+            .into_iter()
+            .flatten()
+            .fold(items[mid].0.end(), T::max);
+
+        Some(max_end[mid])
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    pub fn get(&self, id: usize) -> Option<Interval<T>> {
+        self.positions.get(id).map(|pos| self.items[*pos].0)
+    }
+
+    // Ids of all intervals overlapping `query`, in ascending order.
+    pub fn overlapping(&self, query: &Interval<T>) -> Vec<usize> {
+        let mut res = Vec::new();
+        self.collect_overlapping(query, 0, self.items.len(), &mut res);
+        res.sort_unstable();
+
+        res
+    }
+    fn collect_overlapping(&self, query: &Interval<T>, lo: usize, hi: usize, res: &mut Vec<usize>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        if self.max_end[mid] < query.start() {
+            return;
+        }
+        self.collect_overlapping(query, lo, mid, res);
+
+        let (interval, id) = self.items[mid];
+        if interval.start() > query.end() {
+            return;
+        }
+        if interval.overlaps(query) {
+            res.push(id);
+        }
+        self.collect_overlapping(query, mid + 1, hi, res);
+    }
+
+    pub fn containing_point(&self, p: T) -> Vec<usize> {
+        self.overlapping(&Interval::point(p))
+    }
+    // Ids of the other intervals overlapping the one with the given id.
+    pub fn overlapping_id(&self, id: usize) -> Vec<usize> {
+        self.get(id)
+            .map(|interval| {
+                let mut res = self.overlapping(&interval);
+                res.retain(|other| *other != id);
+                res
+            })
+            .unwrap_or_default()
+    }
+
+    // Every pair either overlaps or has one interval ending before the other
+    // starts, so the overlapping ones are counted as all pairs minus those.
+    pub fn count_overlapping_pairs(&self) -> usize {
+        let mut ends = self.items.iter().map(|(i, _)| i.end()).collect::<Vec<_>>();
+        ends.sort_unstable();
+        let disjoint = self
+            .items
+            .iter()
+            .map(|(i, _)| ends.partition_point(|end| *end < i.start()))
+            .sum::<usize>();
+        let n = self.items.len();
+
+        n * n.saturating_sub(1) / 2 - disjoint
+    }
+}
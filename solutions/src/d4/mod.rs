@@ -3,49 +3,87 @@ use std::{
     io::{BufRead, BufReader},
 };
 
-fn check_num_in_interval(num: u32, l_border: u32, r_border: u32) -> bool {
-    num >= l_border && num <= r_border
-}
+pub mod interval;
+pub mod interval_tree;
+mod report;
+
+use interval::Interval;
+use interval_tree::IntervalTree;
+pub use report::{ContainedPair, CoverageReport, GroupOverlap};
 
-fn check_point_in_range(p: (u32, u32), range: (u32, u32)) -> bool {
-    check_num_in_interval(p.0, range.0, range.1) && check_num_in_interval(p.1, range.0, range.1)
+pub type Assignment = Interval<u32>;
+
+fn parse_group(line: &str) -> Result<Vec<Assignment>, String> {
+    line.split(',').map(|a| a.parse()).collect()
 }
 
-fn check_point_overlaps(p: (u32, u32), range: (u32, u32)) -> bool {
-    check_num_in_interval(p.0, range.0, range.1) || check_num_in_interval(p.1, range.0, range.1)
+fn parse_pair(line: &str) -> Result<(Assignment, Assignment), String> {
+    match parse_group(line)?[..] {
+        [l, r] => Ok((l, r)),
+        _ => Err(format!("Line {line:?} is not a pair of assignments")),
+    }
 }
 
 pub fn exec<F>(f_path: &str, f: F) -> u32
 where
-    F: FnMut(((u32, u32), (u32, u32))) -> bool,
+    F: FnMut(&(Assignment, Assignment)) -> bool,
 {
     let file = File::open(f_path).expect("Failed to open file");
 
     BufReader::new(file)
         .lines()
-        .filter_map(|l| l.ok())
-        .map(|line| {
-            let mut i = line
-                .split(",")
-                .map(|pair| pair.split("-").map(|v| v.parse::<u32>().unwrap()))
-                .map(|mut i| (i.next().unwrap(), i.next().unwrap()));
-
-            (i.next().unwrap(), i.next().unwrap())
-        })
-        .map(f)
-        .filter(|r| *r)
+        .map_while(Result::ok)
+        .map(|line| parse_pair(&line).unwrap())
+        .filter(f)
         .count()
         .try_into()
         .unwrap()
 }
+
+// Groups of assignments with the line number they were read from, blank
+// lines are skipped.
+fn read_groups<R: BufRead>(reader: R) -> Result<Vec<(usize, Vec<Assignment>)>, String> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(idx, line)| {
+            parse_group(&line)
+                .map(|group| (idx + 1, group))
+                .map_err(|e| format!("Line {}: {e}", idx + 1))
+        })
+        .collect()
+}
+
+fn read_groups_from(f_path: &str) -> Result<Vec<(usize, Vec<Assignment>)>, String> {
+    let file = File::open(f_path).map_err(|e| format!("{e}"))?;
+
+    read_groups(BufReader::new(file))
+}
+
+pub fn report(f_path: &str) -> Result<CoverageReport, String> {
+    read_groups_from(f_path).map(|groups| CoverageReport::new(&groups))
+}
+
+// Indexes every assignment of the file, ids follow the reading order.
+pub fn index(f_path: &str) -> Result<IntervalTree<u32>, String> {
+    let all = read_groups_from(f_path)?
+        .into_iter()
+        .flat_map(|(_, group)| group)
+        .collect::<Vec<_>>();
+
+    Ok(IntervalTree::new(&all))
+}
+
 pub fn do_first_part(file_path: &str) -> u32 {
-    let f = |(p1, p2)| check_point_in_range(p1, p2) || check_point_in_range(p2, p1);
+    let f = |(p1, p2): &(Assignment, Assignment)| p1.contains(p2) || p2.contains(p1);
 
     exec(file_path, f)
 }
 
 pub fn do_sec_part(file_path: &str) -> u32 {
-    let f = |(p1, p2)| check_point_overlaps(p1, p2) || check_point_overlaps(p2, p1);
+    let f = |(p1, p2): &(Assignment, Assignment)| p1.overlaps(p2);
 
     exec(file_path, f)
 }
@@ -53,6 +91,7 @@ pub fn do_sec_part(file_path: &str) -> u32 {
 #[cfg(test)]
 mod d4_test {
     use super::*;
+    use crate::test_rng::TestRng;
 
     #[test]
     fn test_first() {
@@ -67,4 +106,213 @@ mod d4_test {
 
         assert_eq!(res, 4);
     }
+
+    #[test]
+    fn test_interval() {
+        let a: Assignment = "2-6".parse().unwrap();
+        let b = Interval::new(5, 9).unwrap();
+
+        assert_eq!(a.len(), Some(5));
+        assert_eq!(a.intersection(&b), Some(Interval::new(5, 6).unwrap()));
+        assert_eq!(a.union(&b), Some(Interval::new(2, 9).unwrap()));
+        assert_eq!(a.union(&Interval::point(8)), None);
+        assert_eq!(a.union(&Interval::point(7)), Some("2-7".parse().unwrap()));
+        assert_eq!(
+            a.difference(&Interval::new(3, 4).unwrap()),
+            vec![Interval::point(2), Interval::new(5, 6).unwrap()]
+        );
+        assert_eq!(a.difference(&b), vec![Interval::new(2, 4).unwrap()]);
+        assert!(b.difference(&Interval::new(0, 10).unwrap()).is_empty());
+        assert!("6-2".parse::<Assignment>().is_err());
+        assert!("6".parse::<Assignment>().is_err());
+    }
+
+    #[test]
+    fn test_interval_at_max() {
+        let full: Assignment = "0-4294967295".parse().unwrap();
+        let top = Interval::new(u32::MAX - 1, u32::MAX).unwrap();
+        let below = Interval::new(10, u32::MAX - 2).unwrap();
+
+        assert_eq!(full.len(), None);
+        assert_eq!(top.len(), Some(2));
+        assert!(top.touches(&below) && below.touches(&top));
+        assert!(top.touches(&full));
+        assert!(!top.touches(&Interval::new(0, 5).unwrap()));
+        assert_eq!(
+            top.union(&below),
+            Some(Interval::new(10, u32::MAX).unwrap())
+        );
+        assert_eq!(
+            full.difference(&top),
+            vec![Interval::new(0, u32::MAX - 2).unwrap()]
+        );
+
+        let set = [top, below]
+            .into_iter()
+            .collect::<interval::IntervalSet<_>>();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.coverage(), Some(u32::MAX - 9));
+        let set = [full, top]
+            .into_iter()
+            .collect::<interval::IntervalSet<_>>();
+        assert_eq!(set.coverage(), None);
+        assert!(set.contains_point(u32::MAX));
+
+        assert_eq!(
+            interval::depth_runs(&[full, top]),
+            vec![(Interval::new(0, u32::MAX - 2).unwrap(), 1), (top, 2)]
+        );
+        assert_eq!(interval::depth_runs(&[top]), vec![(top, 1)]);
+
+        let wide = Interval::<i32>::new(-2_000_000_000, 2_000_000_000).unwrap();
+        assert_eq!(wide.len(), None);
+        assert_eq!(Interval::<i32>::new(-5, 5).unwrap().len(), Some(11));
+        let half = Interval::<i32>::new(-2_000_000_000, 0).unwrap();
+        assert_eq!(half.len(), Some(2_000_000_001));
+        assert_eq!(
+            [half, Interval::new(1, 10).unwrap()]
+                .into_iter()
+                .collect::<interval::IntervalSet<_>>()
+                .coverage(),
+            Some(2_000_000_011)
+        );
+    }
+
+    #[test]
+    fn test_interval_set() {
+        let set = ["10-12", "1-3", "5-6", "4-4", "20-30", "11-25"]
+            .iter()
+            .map(|i| i.parse::<Interval<u64>>().unwrap())
+            .collect::<interval::IntervalSet<_>>();
+
+        assert_eq!(
+            set.iter().map(|i| i.to_string()).collect::<Vec<_>>(),
+            vec!["1-6", "10-30"]
+        );
+        assert_eq!(set.coverage(), Some(27));
+        assert!(set.contains_point(4) && set.contains_point(30));
+        assert!(!set.contains_point(7) && !set.contains_point(31));
+        assert_eq!(set.gaps(), vec![Interval::new(7, 9).unwrap()]);
+    }
+
+    #[test]
+    fn test_report() {
+        let res = report("./src/d4/test.txt").unwrap();
+
+        assert_eq!(
+            res.contained.len(),
+            do_first_part("./src/d4/test.txt") as usize
+        );
+        assert_eq!(
+            res.contained[0],
+            ContainedPair {
+                line: 4,
+                outer: "2-8".parse().unwrap(),
+                inner: "3-7".parse().unwrap(),
+            }
+        );
+        assert_eq!(
+            res.coverage.iter().map(|(_, d)| *d).collect::<Vec<_>>(),
+            vec![4, 5, 7, 8, 6, 4, 1]
+        );
+        assert_eq!(res.coverage[2], ("4-5".parse().unwrap(), 7));
+        assert_eq!(res.largest_gap, None);
+        assert_eq!(res.groups[2].common, Some(Interval::point(7)));
+
+        let res = CoverageReport::new(&[
+            (
+                1,
+                vec![
+                    "1-5".parse().unwrap(),
+                    "4-9".parse().unwrap(),
+                    "5-6".parse().unwrap(),
+                ],
+            ),
+            (2, vec!["20-22".parse().unwrap(), "12-13".parse().unwrap()]),
+        ]);
+        assert_eq!(res.groups[0].common, Some(Interval::point(5)));
+        assert_eq!(res.groups[0].max_depth, 3);
+        assert_eq!(res.groups[1].common, None);
+        assert_eq!(res.largest_gap, Some("14-19".parse().unwrap()));
+        assert!(res
+            .to_string()
+            .contains("line 1: 3 elves, common 5-5, max overlap 3"));
+    }
+
+    #[test]
+    fn test_report_line_numbers() {
+        let groups = read_groups("1-2,3-4\n\n2-8,3-7\n".as_bytes()).unwrap();
+        let res = CoverageReport::new(&groups);
+
+        assert_eq!(res.contained[0].line, 3);
+        assert_eq!(
+            res.groups.iter().map(|g| g.line).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(
+            read_groups("1-2\n\n3-x\n".as_bytes()).unwrap_err(),
+            "Line 3: Failed to parse \"x\" in interval \"3-x\""
+        );
+
+        let empty = CoverageReport::new(&[(1, vec![])]);
+        assert_eq!(empty.groups[0].size, 0);
+        assert_eq!(empty.groups[0].common, None);
+        assert_eq!(empty.groups[0].max_depth, 0);
+        assert_eq!(empty.largest_gap, None);
+    }
+
+    #[test]
+    fn test_interval_tree() {
+        let mut rng = TestRng::new(3);
+        let mut next = |bound| rng.below(bound) as u32;
+        for n in [0, 1, 2, 7, 50, 200] {
+            let intervals = (0..n)
+                .map(|_| {
+                    let start = next(100);
+                    Interval::new(start, start + next(15)).unwrap()
+                })
+                .collect::<Vec<Assignment>>();
+            let tree = IntervalTree::new(&intervals);
+            let brute = |query: &Assignment| {
+                (0..intervals.len())
+                    .filter(|id| intervals[*id].overlaps(query))
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(tree.len(), intervals.len());
+            for (id, interval) in intervals.iter().enumerate() {
+                assert_eq!(tree.get(id), Some(*interval));
+                let mut others = brute(interval);
+                others.retain(|other| *other != id);
+                assert_eq!(tree.overlapping_id(id), others);
+            }
+            assert_eq!(tree.get(intervals.len()), None);
+            for _ in 0..50 {
+                let start = next(120);
+                let query = Interval::new(start, start + next(10)).unwrap();
+                assert_eq!(tree.overlapping(&query), brute(&query));
+                assert_eq!(tree.containing_point(start), brute(&Interval::point(start)));
+            }
+
+            let pairs = (0..intervals.len())
+                .map(|a| {
+                    intervals[a + 1..]
+                        .iter()
+                        .filter(|b| intervals[a].overlaps(b))
+                        .count()
+                })
+                .sum::<usize>();
+            assert_eq!(tree.count_overlapping_pairs(), pairs, "{n} intervals");
+        }
+    }
+
+    #[test]
+    fn test_index() {
+        let tree = index("./src/d4/test.txt").unwrap();
+
+        assert_eq!(tree.len(), 12);
+        assert_eq!(tree.get(0), Some("2-4".parse().unwrap()));
+        assert_eq!(tree.overlapping_id(0), vec![2, 3, 6, 7, 9, 10, 11]);
+        assert_eq!(tree.containing_point(9), vec![5]);
+    }
 }
@@ -0,0 +1,129 @@
+use std::fmt::Display;
+
+use super::{
+    interval::{depth_runs, IntervalSet},
+    Assignment,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ContainedPair {
+    pub line: usize,
+    pub outer: Assignment,
+    pub inner: Assignment,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GroupOverlap {
+    pub line: usize,
+    pub size: usize,
+    // Sections assigned to every elf of the group.
+    pub common: Option<Assignment>,
+    // Largest number of elves of the group sharing a single section.
+    pub max_depth: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CoverageReport {
+    // Runs of sections with the number of elves covering each of them.
+    pub coverage: Vec<(Assignment, usize)>,
+    pub largest_gap: Option<Assignment>,
+    pub contained: Vec<ContainedPair>,
+    pub groups: Vec<GroupOverlap>,
+}
+
+impl CoverageReport {
+    // Builds the report from groups of assignments, each with the number of
+    // the input line it comes from.
+    pub fn new(groups: &[(usize, Vec<Assignment>)]) -> Self {
+        let all = groups
+            .iter()
+            .flat_map(|(_, group)| group)
+            .copied()
+            .collect::<Vec<_>>();
+        let largest_gap = all
+            .iter()
+            .copied()
+            .collect::<IntervalSet<_>>()
+            .gaps()
+            .into_iter()
+            .reduce(|best, gap| {
+                if gap.end() - gap.start() > best.end() - best.start() {
+                    gap
+                } else {
+                    best
+                }
+            });
+
+        let mut contained = Vec::new();
+        for (line, group) in groups {
+            for (pos, a) in group.iter().enumerate() {
+                for b in &group[pos + 1..] {
+                    let (outer, inner) = if a.contains(b) { (a, b) } else { (b, a) };
+                    if outer.contains(inner) {
+                        contained.push(ContainedPair {
+                            line: *line,
+                            outer: *outer,
+                            inner: *inner,
+                        });
+                    }
+                }
+            }
+        }
+
+        let groups = groups
+            .iter()
+            .map(|(line, group)| GroupOverlap {
+                line: *line,
+                size: group.len(),
+                common: group.split_first().and_then(|(first, rest)| {
+                    rest.iter().try_fold(*first, |acc, a| acc.intersection(a))
+                }),
+                max_depth: depth_runs(group)
+                    .into_iter()
+                    .map(|(_, depth)| depth)
+                    .max()
+                    .unwrap_or(0),
+            })
+            .collect();
+
+        Self {
+            coverage: depth_runs(&all),
+            largest_gap,
+            contained,
+            groups,
+        }
+    }
+}
+
+impl Display for CoverageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "coverage:")?;
+        for (sections, elves) in &self.coverage {
+            writeln!(f, "  {sections}: {elves}")?;
+        }
+        match self.largest_gap {
+            // A gap lies between two intervals, so its length always fits.
+            Some(gap) => writeln!(f, "largest gap: {gap} ({} sections)", gap.len().unwrap())?,
+            None => writeln!(f, "largest gap: none")?,
+        }
+        writeln!(f, "fully contained:")?;
+        for pair in &self.contained {
+            writeln!(
+                f,
+                "  line {}: {} contains {}",
+                pair.line, pair.outer, pair.inner
+            )?;
+        }
+        writeln!(f, "groups:")?;
+        for group in &self.groups {
+            write!(f, "  line {}: {} elves, common ", group.line, group.size)?;
+            match group.common {
+                Some(common) => write!(f, "{common}")?,
+                None => write!(f, "none")?,
+            }
+            writeln!(f, ", max overlap {}", group.max_depth)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use super::Crate;
+
+pub trait Crane {
+    // Moves the top `count` crates of `from` onto `to`, `count` never exceeds
+    // the height of `from`.
+    fn lift(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize);
+}
+
+// Moves crates one at a time, so the moved part ends up reversed.
+#[derive(Clone, Copy, Debug)]
+pub struct CrateMover9000;
+impl Crane for CrateMover9000 {
+    fn lift(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize) {
+        let split = from.len() - count;
+        to.extend(from.drain(split..).rev());
+    }
+}
+
+// Moves all the crates at once, keeping their order.
+#[derive(Clone, Copy, Debug)]
+pub struct CrateMover9001;
+impl Crane for CrateMover9001 {
+    fn lift(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize) {
+        let split = from.len() - count;
+        to.extend(from.drain(split..));
+    }
+}
+
+// Moves at most `capacity` crates per lift, each lift keeping its order.
+#[derive(Clone, Copy, Debug)]
+pub struct LimitedCrane {
+    capacity: usize,
+}
+impl LimitedCrane {
+    pub fn new(capacity: usize) -> Result<Self, String> {
+        if capacity == 0 {
+            return Err("Crane must be able to lift at least one crate".to_string());
+        }
+
+        Ok(Self { capacity })
+    }
+}
+impl Crane for LimitedCrane {
+    fn lift(&self, from: &mut Vec<Crate>, to: &mut Vec<Crate>, count: usize) {
+        let mut left = count;
+        while left > 0 {
+            let lifted = left.min(self.capacity);
+            let split = from.len() - lifted;
+            to.extend(from.drain(split..));
+            left -= lifted;
+        }
+    }
+}
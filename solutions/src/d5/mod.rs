@@ -1,113 +1,204 @@
-use std::{fs::read_to_string, sync::RwLock};
+use std::{fs::read_to_string, str::FromStr};
 
-#[derive(Debug)]
-struct Move {
-    count: usize,
-    from: usize,
-    to: usize,
+mod crane;
+mod simulator;
+mod stockpile;
+
+pub use crane::{Crane, CrateMover9000, CrateMover9001, LimitedCrane};
+pub use simulator::{diff, first_divergence, Simulator, StackDiff};
+pub use stockpile::{InvalidMovePolicy, MoveError, MoveErrorKind, Stockpile};
+
+pub type Crate = String;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub count: usize,
+    pub from: usize,
+    pub to: usize,
+    // Line of the procedure file the move was read from, 0 if it wasn't.
+    pub line: usize,
 }
 impl Move {
-    fn new(count: usize, from: usize, to: usize) -> Self {
-        Self { count, from, to }
+    pub fn new(count: usize, from: usize, to: usize) -> Self {
+        Self {
+            count,
+            from,
+            to,
+            line: 0,
+        }
     }
 }
-type StockpileStack = RwLock<Vec<char>>;
 
-fn parse(file_path: &str) -> (Vec<StockpileStack>, Vec<Move>) {
-    let file = read_to_string(file_path).unwrap();
-    let mut splitted = file.split("\n\n");
-    let (stock, moves) = (splitted.next().unwrap(), splitted.next().unwrap());
+pub fn parse(file_path: &str) -> Result<(Stockpile, Vec<Move>), String> {
+    let file = read_to_string(file_path).map_err(|e| format!("{file_path}: {e}"))?;
 
-    (parse_stock(stock), parse_commands(moves))
+    parse_input(&file)
 }
 
-fn parse_stock(raw: &str) -> Vec<StockpileStack> {
-    let mut by_rows = raw
-        .split("\n")
-        .map(|line| line.chars().skip(1).step_by(4).collect::<Vec<_>>())
-        .collect::<Vec<_>>();
+fn parse_input(input: &str) -> Result<(Stockpile, Vec<Move>), String> {
+    let (stock, moves) = input.split_once("\n\n").ok_or_else(|| {
+        format!(
+            "Line {}: expected an empty line between the drawing and the moves",
+            input.lines().count() + 1
+        )
+    })?;
+
+    let first_move_line = stock.split('\n').count() + 2;
 
-    by_rows.pop();
-    let stack_num = by_rows[0].len();
-    let max_height = stack_num * by_rows.len();
-    let mut result: Vec<StockpileStack> = Vec::with_capacity(stack_num);
+    Ok((parse_stock(stock)?, parse_commands(moves, first_move_line)?))
+}
 
-    for _ in 0..stack_num {
-        result.push(RwLock::new(Vec::with_capacity(max_height)));
+// Spans of the `[label]` crates of a drawing row with their trimmed labels.
+// Spans count chars like the renderer does, so labels needn't be ASCII.
+fn parse_row(line: &str) -> Result<Vec<(usize, usize, &str)>, String> {
+    let column = |byte: usize| line[..byte].chars().count();
+    let mut crates = Vec::new();
+    let mut rest = 0;
+    while let Some(open) = line[rest..].find('[').map(|idx| rest + idx) {
+        let close = line[open..]
+            .find(']')
+            .map(|idx| open + idx)
+            .ok_or_else(|| format!("unclosed crate in row {line:?}"))?;
+        let label = line[open + 1..close].trim();
+        crates.push((column(open), column(close) + 1, label));
+        rest = close + 1;
     }
 
-    while let Some(row) = by_rows.pop() {
-        for (idx, sym) in row.into_iter().enumerate() {
-            if sym != ' ' {
-                result[idx].write().unwrap().push(sym);
-            }
+    Ok(crates)
+}
+
+// Stack columns are taken from the numbering row: every crate belongs to the
+// stack whose number lies under it, so labels can be of any width.
+fn parse_stock(raw: &str) -> Result<Stockpile, String> {
+    let mut rows = raw.lines().collect::<Vec<_>>();
+    let numbering = rows.pop().ok_or("Stockpile drawing is empty")?;
+    let numbering_line = rows.len() + 1;
+
+    let mut columns = Vec::new();
+    let numbers = numbering
+        .split(' ')
+        .scan(0, |pos, word| {
+            let start = *pos;
+            *pos += word.chars().count() + 1;
+            Some((start, word))
+        })
+        .filter(|(_, word)| !word.is_empty());
+    for (idx, (start, number)) in numbers.enumerate() {
+        if number.parse::<usize>() != Ok(idx + 1) {
+            return Err(format!(
+                "Line {numbering_line}: stack {number:?} found where stack {} was expected",
+                idx + 1
+            ));
         }
+        columns.push((start, start + number.len()));
     }
 
-    result
+    let mut result = vec![Vec::with_capacity(rows.len()); columns.len()];
+    for (idx, row) in rows.into_iter().enumerate().rev() {
+        let at_line = |e| format!("Line {}: {e}", idx + 1);
+        let mut filled = vec![false; columns.len()];
+        for (start, end, label) in parse_row(row).map_err(at_line)? {
+            let stack = columns
+                .iter()
+                .position(|(s, e)| *s < end && start < *e)
+                .filter(|stack| !filled[*stack])
+                .ok_or_else(|| at_line(format!("crate [{label}] has no stack of its own")))?;
+            filled[stack] = true;
+            result[stack].push(label.to_string());
+        }
+    }
+
+    Ok(Stockpile::new(result))
 }
 
-fn parse_commands(raw: &str) -> Vec<Move> {
-    raw.split("\n")
-        .map(|line| {
-            if line.is_empty() {
-                None
-            } else {
-                Some(
-                    line.split_whitespace()
-                        .skip(1)
-                        .step_by(2)
-                        .map(|n| n.parse::<usize>().unwrap()),
-                )
-            }
+fn parse_commands(raw: &str, first_line: usize) -> Result<Vec<Move>, String> {
+    raw.split('\n')
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(idx, line)| {
+            let line_no = first_line + idx;
+            let numbers = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["move", count, "from", from, "to", to] => [count, from, to].map(str::parse),
+                _ => {
+                    return Err(format!(
+                        "Line {line_no}: expected \"move N from N to N\", found {line:?}"
+                    ))
+                }
+            };
+            let [count, from, to] =
+                numbers.map(|n| n.map_err(|e| format!("Line {line_no}: {e} in {line:?}")));
+
+            Ok(Move {
+                line: line_no,
+                ..Move::new(count?, from?, to?)
+            })
         })
-        .filter_map(|l| l)
-        .map(|mut p| (p.next().unwrap(), p.next().unwrap(), p.next().unwrap()))
-        .map(|v| Move::new(v.0, v.1, v.2))
-        .collect::<Vec<_>>()
+        .collect()
 }
 
-pub fn do_first_part(file_path: &str) -> String {
-    let (stock, cmds) = parse(file_path);
-    for cmd in cmds {
-        if cmd.from != cmd.to {
-            let target_len = { stock[cmd.from - 1].read().unwrap().len() };
-
-            let mut from_write = stock[cmd.from - 1].write().unwrap();
-            let moved = from_write.drain(target_len - cmd.count..).rev();
+impl FromStr for Stockpile {
+    type Err = String;
 
-            stock[cmd.to - 1].write().unwrap().extend(moved)
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_stock(s)
     }
-    stock
-        .iter()
-        .map(|c| c.read().unwrap().iter().rev().next().map(|c| c.clone()))
-        .filter_map(|c| c)
-        .collect::<String>()
 }
 
-pub fn do_sec_part(file_path: &str) -> String {
-    let (stock, cmds) = parse(file_path);
-    for cmd in cmds {
-        if cmd.from != cmd.to {
-            let target_len = { stock[cmd.from - 1].read().unwrap().len() };
+// Drawings of the stockpile before the procedure and after each of its moves.
+pub fn replay<'a>(
+    stock: &Stockpile,
+    moves: &'a [Move],
+    crane: &'a impl Crane,
+) -> impl Iterator<Item = String> + 'a {
+    let mut stock = stock.clone();
+    let initial = stock.to_string();
 
-            let mut from_write = stock[cmd.from - 1].write().unwrap();
-            let moved = from_write.drain(target_len - cmd.count..);
+    std::iter::once(initial).chain(moves.iter().map(move |mv| {
+        stock.apply(mv, crane);
+        stock.to_string()
+    }))
+}
 
-            stock[cmd.to - 1].write().unwrap().extend(moved)
-        }
-    }
-    stock
-        .iter()
-        .map(|c| c.read().unwrap().iter().rev().next().map(|c| c.clone()))
-        .filter_map(|c| c)
-        .collect::<String>()
+// Runs the procedure with the given crane and returns the top crates.
+pub fn run(file_path: &str, crane: &impl Crane) -> String {
+    let (mut stock, cmds) = parse(file_path).unwrap();
+    stock.apply_all(&cmds, crane);
+
+    stock.tops()
+}
+
+// Like `run`, but reports malformed input and validates every move against
+// the current stockpile. Moves skipped or clamped under the policy are
+// returned along with the tops.
+pub fn run_checked(
+    file_path: &str,
+    crane: &impl Crane,
+    policy: InvalidMovePolicy,
+) -> Result<(String, Vec<MoveError>), String> {
+    let (mut stock, cmds) = parse(file_path)?;
+    let invalid = stock
+        .apply_checked(&cmds, crane, policy)
+        .map_err(|e| e.to_string())?;
+
+    Ok((stock.tops(), invalid))
+}
+
+pub fn do_first_part(file_path: &str) -> String {
+    run(file_path, &CrateMover9000)
+}
+
+pub fn do_sec_part(file_path: &str) -> String {
+    run(file_path, &CrateMover9001)
 }
 
 #[cfg(test)]
 mod d5_test {
     use super::*;
+    use crate::test_rng::TestRng;
+
+    fn crates(labels: &str) -> Vec<Crate> {
+        labels.chars().map(String::from).collect()
+    }
 
     #[test]
     fn test_first() {
@@ -122,4 +213,265 @@ mod d5_test {
 
         assert_eq!(res, "MCD".to_owned());
     }
+
+    #[test]
+    fn test_limited_crane() {
+        let one = run("./src/d5/test.txt", &LimitedCrane::new(1).unwrap());
+        let all = run("./src/d5/test.txt", &LimitedCrane::new(3).unwrap());
+
+        assert_eq!(one, "CMZ".to_owned());
+        assert_eq!(all, "MCD".to_owned());
+        assert!(LimitedCrane::new(0).is_err());
+
+        let (mut from, mut to) = (crates("ABCDE"), vec![]);
+        LimitedCrane::new(2).unwrap().lift(&mut from, &mut to, 5);
+        assert_eq!(to, crates("DEBCA"));
+    }
+
+    #[test]
+    fn test_stockpile() {
+        let mut stock = Stockpile::new(vec![crates("AB"), vec![], crates("C")]);
+        stock.apply(&Move::new(2, 1, 3), &CrateMover9000);
+        stock.apply(&Move::new(1, 3, 2), &CrateMover9001);
+
+        assert_eq!(stock.tops(), "AB".to_owned());
+        assert_eq!(stock.stack(3), Some(&crates("CB")[..]));
+        assert_eq!(stock.stack(0), None);
+        assert_eq!(
+            stock.stacks().map(|s| s.len()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let (stock, moves) = parse("./src/d5/test.txt").unwrap();
+        assert_eq!(moves[1].line, 7);
+        assert_eq!(
+            stock.validate(&moves[1]).unwrap_err().heights,
+            vec![2, 3, 1]
+        );
+
+        let bad = [Move::new(1, 4, 1), Move::new(4, 2, 1), Move::new(1, 2, 3)];
+        let err = stock
+            .clone()
+            .apply_checked(&bad, &CrateMover9000, InvalidMovePolicy::Fail);
+        assert_eq!(err.unwrap_err().kind, MoveErrorKind::UnknownStack(4));
+
+        let mut skipped = stock.clone();
+        let res = skipped.apply_checked(&bad, &CrateMover9000, InvalidMovePolicy::Skip);
+        assert_eq!(res.unwrap().len(), 2);
+        assert_eq!(skipped.tops(), "NCD".to_owned());
+
+        let mut clamped = stock.clone();
+        let res = clamped
+            .apply_checked(&bad, &CrateMover9000, InvalidMovePolicy::Clamp)
+            .unwrap();
+        assert_eq!(res[1].kind, MoveErrorKind::NotEnoughCrates { available: 3 });
+        assert_eq!(clamped.tops(), "MP".to_owned());
+        assert_eq!(
+            res[1].to_string(),
+            "Line 0: move 4 from 2 to 1: stack 2 has only 3 crates (stack heights [2, 3, 1])"
+        );
+
+        let res = run_checked(
+            "./src/d5/test.txt",
+            &CrateMover9001,
+            InvalidMovePolicy::Fail,
+        );
+        assert_eq!(res, Ok(("MCD".to_owned(), vec![])));
+        assert!(run_checked(
+            "./src/d5/missing.txt",
+            &CrateMover9001,
+            InvalidMovePolicy::Fail
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let drawing = "[A] [B]\n 1   2 ";
+        let (stock, moves) = parse_input(&format!("{drawing}\n\nmove 1 from 1 to 2\n")).unwrap();
+        assert_eq!(stock.tops(), "AB".to_owned());
+        assert_eq!(
+            moves,
+            vec![Move {
+                line: 4,
+                ..Move::new(1, 1, 2)
+            }]
+        );
+
+        let err = |input: &str| parse_input(input).unwrap_err();
+        assert_eq!(
+            err(&format!(
+                "{drawing}\n\nmove 1 from 1 to 2\nmove x from 1 to 2\n"
+            )),
+            "Line 5: invalid digit found in string in \"move x from 1 to 2\""
+        );
+        assert_eq!(
+            err(&format!("{drawing}\n\nmove 1 from 2\n")),
+            "Line 4: expected \"move N from N to N\", found \"move 1 from 2\""
+        );
+        assert_eq!(
+            err(&format!("{drawing}\nmove 1 from 1 to 2\n")),
+            "Line 4: expected an empty line between the drawing and the moves"
+        );
+        assert_eq!(
+            err("[A] [B\n 1   2 \n\nmove 1 from 1 to 2\n"),
+            "Line 1: unclosed crate in row \"[A] [B\""
+        );
+        assert_eq!(
+            err("[A]\n[B] [C]\n 1   3 \n\n"),
+            "Line 3: stack \"3\" found where stack 2 was expected"
+        );
+        assert_eq!(
+            err("    [A]\n[B]\n 1 \n\n"),
+            "Line 1: crate [A] has no stack of its own"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let file = read_to_string("./src/d5/test.txt").unwrap();
+        let drawing = file.split("\n\n").next().unwrap();
+        let (stock, moves) = parse("./src/d5/test.txt").unwrap();
+
+        assert_eq!(stock.to_string(), drawing);
+        assert_eq!(drawing.parse::<Stockpile>().unwrap(), stock);
+
+        let steps = replay(&stock, &moves, &CrateMover9000).collect::<Vec<_>>();
+        assert_eq!(steps.len(), 5);
+        assert_eq!(steps[0], drawing);
+        assert_eq!(
+            steps[4],
+            "        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 "
+        );
+        for step in steps {
+            assert_eq!(step.parse::<Stockpile>().unwrap().to_string(), step);
+        }
+        assert_eq!(Stockpile::new(vec![vec![], vec![]]).to_string(), " 1   2 ");
+    }
+
+    #[test]
+    fn test_simulator() {
+        let (stock, moves) = parse("./src/d5/test.txt").unwrap();
+        let mut sim = Simulator::new(stock.clone(), moves.clone(), CrateMover9000);
+
+        while sim.step_forward().unwrap().is_some() {}
+        assert_eq!(sim.position(), 4);
+        assert_eq!(sim.state().tops(), "CMZ".to_owned());
+        assert_eq!(sim.step_back(), Some(moves[3]));
+        sim.jump_to(0).unwrap();
+        assert_eq!(sim.state(), &stock);
+        assert_eq!(sim.step_back(), None);
+        sim.jump_to(10).unwrap();
+        assert_eq!(sim.position(), 4);
+
+        let history = sim.history().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            replay(&stock, &moves, &CrateMover9000).collect::<Vec<_>>()
+        );
+
+        let divergence = first_divergence(&stock, &moves, &CrateMover9000, &CrateMover9001);
+        assert_eq!(
+            divergence.unwrap(),
+            Some((
+                2,
+                vec![StackDiff {
+                    stack: 3,
+                    left: crates("PDNZ"),
+                    right: crates("PZND"),
+                }]
+            ))
+        );
+
+        let mut bad = Simulator::new(stock, vec![Move::new(5, 1, 2)], CrateMover9001);
+        assert!(bad.step_forward().is_err());
+        assert_eq!(bad.history().count(), 1);
+    }
+
+    #[test]
+    fn test_wide_stockpile() {
+        let mut stacks = (0..12).map(|s| vec![format!("S{s}")]).collect::<Vec<_>>();
+        stacks[11].push("TOP".to_string());
+        stacks[0].push("A".to_string());
+        let stock = Stockpile::new(stacks);
+        let drawing = stock.to_string();
+
+        assert_eq!(
+            drawing.lines().next().unwrap(),
+            format!("[ A ]{}[TOP]", " ".repeat(61))
+        );
+        assert!(drawing.lines().nth(1).unwrap().starts_with("[S0 ] [S1 ]"));
+        assert!(drawing.ends_with("  11    12  "));
+        assert_eq!(drawing.parse::<Stockpile>().unwrap(), stock);
+        assert_eq!(stock.tops(), "AS1S2S3S4S5S6S7S8S9S10TOP".to_owned());
+
+        let narrow = Stockpile::new((0..10).map(|s| crates(&s.to_string())).collect());
+        let drawing = narrow.to_string();
+        assert!(drawing.ends_with(" 9  10 "));
+        assert_eq!(drawing.parse::<Stockpile>().unwrap(), narrow);
+
+        let mut moved = drawing.parse::<Stockpile>().unwrap();
+        moved.apply(&Move::new(1, 10, 1), &CrateMover9000);
+        assert_eq!(moved.stack(1), Some(&crates("09")[..]));
+
+        let accented = Stockpile::new(vec![crates("é"), crates("é"), crates("b")]);
+        assert_eq!(accented.to_string().parse::<Stockpile>(), Ok(accented));
+        let mixed = Stockpile::new(vec![vec!["Æsir".into(), "ü".into()], crates("xyz")]);
+        assert_eq!(mixed.to_string().parse::<Stockpile>(), Ok(mixed));
+
+        assert!("[A]\n 1   3 ".parse::<Stockpile>().is_err());
+        assert!("    [A]\n 1 ".parse::<Stockpile>().is_err());
+        assert!("[A\n 1 ".parse::<Stockpile>().is_err());
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --release -- --ignored --nocapture"]
+    fn bench_stockpile_vs_rwlock() {
+        use std::{sync::RwLock, time::Instant};
+
+        let stacks = 9;
+        let mut heights = vec![20usize; stacks];
+        let mut rng = TestRng::new(7);
+        let mut next = |bound| rng.below(bound);
+        let moves = (0..3_000_000)
+            .map(|_| {
+                let from = next(stacks);
+                let to = (from + 1 + next(stacks - 1)) % stacks;
+                let count = next(heights[from].min(5) + 1);
+                heights[from] -= count;
+                heights[to] += count;
+                Move::new(count, from + 1, to + 1)
+            })
+            .collect::<Vec<_>>();
+        let initial = (0..stacks)
+            .map(|s| {
+                (0..20)
+                    .map(|h| char::from(b'A' + ((s + h) % 26) as u8).to_string())
+                    .collect()
+            })
+            .collect::<Vec<Vec<Crate>>>();
+
+        let start = Instant::now();
+        let locked = initial.iter().cloned().map(RwLock::new).collect::<Vec<_>>();
+        for cmd in &moves {
+            let target_len = { locked[cmd.from - 1].read().unwrap().len() };
+            let mut from_write = locked[cmd.from - 1].write().unwrap();
+            let moved = from_write.drain(target_len - cmd.count..);
+            locked[cmd.to - 1].write().unwrap().extend(moved)
+        }
+        let locked_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut stock = Stockpile::new(initial);
+        stock.apply_all(&moves, &CrateMover9001);
+        let owned_time = start.elapsed();
+
+        println!("rwlock: {locked_time:?}, stockpile: {owned_time:?}");
+        let locked = locked.into_iter().map(|s| s.into_inner().unwrap());
+        assert!(locked.eq(stock.stacks().map(|s| s.to_vec())));
+        assert!(owned_time < locked_time);
+    }
 }
@@ -0,0 +1,123 @@
+use super::{Crane, Crate, Move, MoveError, Stockpile};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackDiff {
+    pub stack: usize,
+    pub left: Vec<Crate>,
+    pub right: Vec<Crate>,
+}
+
+// Stacks whose crates differ between the two stockpiles.
+pub fn diff(left: &Stockpile, right: &Stockpile) -> Vec<StackDiff> {
+    (1..=left.len().max(right.len()))
+        .filter_map(|stack| {
+            let (l, r) = (
+                left.stack(stack).unwrap_or_default(),
+                right.stack(stack).unwrap_or_default(),
+            );
+            (l != r).then(|| StackDiff {
+                stack,
+                left: l.to_vec(),
+                right: r.to_vec(),
+            })
+        })
+        .collect()
+}
+
+// Runs a procedure move by move, keeping an undo log so it can also be
+// rewound. The log stores the crates each move took from its source stack.
+#[derive(Clone, Debug)]
+pub struct Simulator<C> {
+    stock: Stockpile,
+    moves: Vec<Move>,
+    crane: C,
+    undo_log: Vec<Vec<Crate>>,
+}
+
+impl<C: Crane> Simulator<C> {
+    pub fn new(stock: Stockpile, moves: Vec<Move>, crane: C) -> Self {
+        Self {
+            stock,
+            moves,
+            crane,
+            undo_log: Vec::new(),
+        }
+    }
+    pub fn state(&self) -> &Stockpile {
+        &self.stock
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+    // Number of moves applied so far.
+    pub fn position(&self) -> usize {
+        self.undo_log.len()
+    }
+
+    // Applies the next move, returns `None` once the procedure is over.
+    pub fn step_forward(&mut self) -> Result<Option<Move>, MoveError> {
+        let Some(mv) = self.moves.get(self.position()).copied() else {
+            return Ok(None);
+        };
+        self.stock.validate(&mv)?;
+        let source = self.stock.stack(mv.from).unwrap();
+        let taken = source[source.len() - mv.count..].to_vec();
+        self.stock.apply(&mv, &self.crane);
+        self.undo_log.push(taken);
+
+        Ok(Some(mv))
+    }
+    // Reverts the last applied move, returns `None` at the start.
+    pub fn step_back(&mut self) -> Option<Move> {
+        let taken = self.undo_log.pop()?;
+        let mv = self.moves[self.position()];
+        self.stock.revert(&mv, taken);
+
+        Some(mv)
+    }
+    // Moves to the state after the first `position` moves, or to the end when
+    // the procedure is shorter.
+    pub fn jump_to(&mut self, position: usize) -> Result<(), MoveError> {
+        while self.position() > position {
+            self.step_back();
+        }
+        while self.position() < position && self.step_forward()?.is_some() {}
+
+        Ok(())
+    }
+}
+
+impl<C: Crane + Clone> Simulator<C> {
+    // States from the initial stockpile up to the end of the procedure, the
+    // iteration stops early at the first invalid move.
+    pub fn history(&self) -> impl Iterator<Item = Stockpile> {
+        let mut sim = self.clone();
+        while sim.step_back().is_some() {}
+        let initial = sim.stock.clone();
+
+        std::iter::once(initial).chain(std::iter::from_fn(move || {
+            sim.step_forward().ok().flatten().map(|_| sim.stock.clone())
+        }))
+    }
+}
+
+// First position at which running the procedure with the two cranes gives
+// different stockpiles, together with the differing stacks.
+pub fn first_divergence(
+    stock: &Stockpile,
+    moves: &[Move],
+    left: &impl Crane,
+    right: &impl Crane,
+) -> Result<Option<(usize, Vec<StackDiff>)>, MoveError> {
+    let (mut l, mut r) = (stock.clone(), stock.clone());
+    for (idx, mv) in moves.iter().enumerate() {
+        l.try_apply(mv, left)?;
+        r.try_apply(mv, right)?;
+        let diffs = diff(&l, &r);
+        if !diffs.is_empty() {
+            return Ok(Some((idx + 1, diffs)));
+        }
+    }
+
+    Ok(None)
+}
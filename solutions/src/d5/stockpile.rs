@@ -0,0 +1,199 @@
+use std::fmt::Display;
+
+use super::{Crane, Crate, Move};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveErrorKind {
+    UnknownStack(usize),
+    NotEnoughCrates { available: usize },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveError {
+    pub mv: Move,
+    pub kind: MoveErrorKind,
+    // Heights of all the stacks right before the move.
+    pub heights: Vec<usize>,
+}
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Move {
+            count,
+            from,
+            to,
+            line,
+        } = self.mv;
+        write!(f, "Line {line}: move {count} from {from} to {to}: ")?;
+        match self.kind {
+            MoveErrorKind::UnknownStack(n) => write!(f, "there is no stack {n}")?,
+            MoveErrorKind::NotEnoughCrates { available } => {
+                write!(f, "stack {from} has only {available} crates")?
+            }
+        }
+        write!(f, " (stack heights {:?})", self.heights)
+    }
+}
+
+// What to do with a move that can't be performed on the current stockpile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidMovePolicy {
+    Fail,
+    Skip,
+    // Move as many crates as there are, moves between unknown stacks are skipped.
+    Clamp,
+}
+
+// Stacks of crates, numbered from 1 like in the puzzle drawing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stockpile {
+    stacks: Vec<Vec<Crate>>,
+}
+
+impl Stockpile {
+    pub fn new(stacks: Vec<Vec<Crate>>) -> Self {
+        Self { stacks }
+    }
+    pub fn len(&self) -> usize {
+        self.stacks.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+    // Crates of the stack from bottom to top.
+    pub fn stack(&self, number: usize) -> Option<&[Crate]> {
+        number
+            .checked_sub(1)
+            .and_then(|idx| self.stacks.get(idx))
+            .map(|s| &s[..])
+    }
+    pub fn stacks(&self) -> impl Iterator<Item = &[Crate]> {
+        self.stacks.iter().map(|s| &s[..])
+    }
+    pub fn tops(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|s| s.last())
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn heights(&self) -> Vec<usize> {
+        self.stacks.iter().map(|s| s.len()).collect()
+    }
+
+    pub fn validate(&self, mv: &Move) -> Result<(), MoveError> {
+        let error = |kind| MoveError {
+            mv: *mv,
+            kind,
+            heights: self.heights(),
+        };
+        for number in [mv.from, mv.to] {
+            if self.stack(number).is_none() {
+                return Err(error(MoveErrorKind::UnknownStack(number)));
+            }
+        }
+        let available = self.stacks[mv.from - 1].len();
+        if available < mv.count {
+            return Err(error(MoveErrorKind::NotEnoughCrates { available }));
+        }
+
+        Ok(())
+    }
+    pub fn try_apply(&mut self, mv: &Move, crane: &impl Crane) -> Result<(), MoveError> {
+        self.validate(mv)?;
+        self.apply(mv, crane);
+
+        Ok(())
+    }
+    // Applies the moves, invalid ones are handled according to the policy and
+    // returned so they can be reported.
+    pub fn apply_checked<'a>(
+        &mut self,
+        moves: impl IntoIterator<Item = &'a Move>,
+        crane: &impl Crane,
+        policy: InvalidMovePolicy,
+    ) -> Result<Vec<MoveError>, MoveError> {
+        let mut invalid = Vec::new();
+        for mv in moves {
+            let Err(e) = self.try_apply(mv, crane) else {
+                continue;
+            };
+            match (policy, e.kind) {
+                (InvalidMovePolicy::Fail, _) => return Err(e),
+                (InvalidMovePolicy::Clamp, MoveErrorKind::NotEnoughCrates { available }) => {
+                    self.apply(
+                        &Move {
+                            count: available,
+                            ..*mv
+                        },
+                        crane,
+                    );
+                }
+                _ => (),
+            }
+            invalid.push(e);
+        }
+
+        Ok(invalid)
+    }
+
+    // Applies the move without validation, panics on invalid moves.
+    pub fn apply(&mut self, mv: &Move, crane: &impl Crane) {
+        if mv.from != mv.to {
+            let (from, to) = self.pair_mut(mv.from - 1, mv.to - 1);
+            crane.lift(from, to, mv.count);
+        }
+    }
+    // Undoes an applied move given the crates it took from its source stack.
+    pub(super) fn revert(&mut self, mv: &Move, taken: Vec<Crate>) {
+        let to = &mut self.stacks[mv.to - 1];
+        to.truncate(to.len() - mv.count);
+        self.stacks[mv.from - 1].extend(taken);
+    }
+    pub fn apply_all<'a>(&mut self, moves: impl IntoIterator<Item = &'a Move>, crane: &impl Crane) {
+        for mv in moves {
+            self.apply(mv, crane);
+        }
+    }
+
+    fn pair_mut(&mut self, a: usize, b: usize) -> (&mut Vec<Crate>, &mut Vec<Crate>) {
+        if a < b {
+            let (left, right) = self.stacks.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        } else {
+            let (left, right) = self.stacks.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        }
+    }
+}
+
+// Draws the stockpile the way the puzzle input does, numbered base row
+// included. All columns share the width of the widest label or stack number.
+impl Display for Stockpile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label_width = self.stacks.iter().flatten().map(|c| c.chars().count());
+        let width = label_width
+            .max()
+            .unwrap_or(1)
+            .max(1)
+            .max(self.stacks.len().to_string().len().saturating_sub(2))
+            + 2;
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row = self
+                .stacks
+                .iter()
+                .map(|s| match s.get(level) {
+                    Some(c) => format!("[{c:^0$}]", width - 2),
+                    None => " ".repeat(width),
+                })
+                .collect::<Vec<_>>();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        let base = (1..=self.stacks.len())
+            .map(|n| format!("{n:^width$}"))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", base.join(" "))
+    }
+}
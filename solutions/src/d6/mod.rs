@@ -1,33 +1,190 @@
-use std::{collections::HashSet, fs::read_to_string};
+use std::{
+    fs::{read, File},
+    io::{self, Read},
+};
 
-pub fn do_first_part(file_path: &str) -> u32 {
-    let file = read_to_string(file_path).unwrap();
+// Length of the longest run of distinct bytes ending at the last pushed one,
+// maintained in O(1) per byte with the position each byte value was last seen.
+#[derive(Clone, Debug)]
+struct DistinctRun {
+    last_seen: [usize; 256],
+    start: usize,
+    pos: usize,
+}
+impl DistinctRun {
+    fn new() -> Self {
+        Self {
+            last_seen: [0; 256],
+            start: 0,
+            pos: 0,
+        }
+    }
+    // Positions are stored shifted by one so zero means "never seen".
+    fn push(&mut self, byte: u8) -> usize {
+        let seen = &mut self.last_seen[usize::from(byte)];
+        self.start = self.start.max(*seen);
+        self.pos += 1;
+        *seen = self.pos;
 
-    find_marker(&file, 4)
+        self.pos - self.start
+    }
 }
 
-pub fn do_sec_part(file_path: &str) -> u32 {
-    let file = read_to_string(file_path).unwrap();
+// Position right after the first window of `win_size` distinct bytes.
+pub fn find_marker(data: &[u8], win_size: usize) -> Option<usize> {
+    if win_size == 0 {
+        return Some(0);
+    }
+    let mut run = DistinctRun::new();
 
-    find_marker(&file, 14)
+    data.iter()
+        .position(|b| run.push(*b) >= win_size)
+        .map(|idx| idx + 1)
 }
 
-pub fn find_marker(data: &String, win_size: usize) -> u32 {
-    let mut dict = HashSet::with_capacity(win_size);
-    let chars = data.chars().collect::<Vec<_>>();
+// Same as `find_marker`, reading the data only as far as the marker.
+pub fn find_marker_in<R: Read>(reader: R, win_size: usize) -> io::Result<Option<usize>> {
+    let events = detect_in(reader, &[win_size])?;
 
-    for (win_start_idx, win) in chars[..].windows(win_size).enumerate() {
-        for ch in win.iter() {
-            dict.insert(ch);
-        }
+    Ok(events.first().map(|e| e.offset))
+}
 
-        if dict.len() == win_size {
-            return u32::try_from(win_start_idx + win_size).unwrap();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarkerEvent {
+    pub win_size: usize,
+    // Absolute position right after the marker.
+    pub offset: usize,
+}
+
+// Push-based marker search for data arriving in chunks of any size, e.g. from
+// a socket or a pipe. Every window size produces a single event, as soon as
+// the byte completing its first marker is fed.
+#[derive(Clone, Debug)]
+pub struct MarkerDetector {
+    run: DistinctRun,
+    pending: Vec<usize>,
+    // Bytes fed, scanning stops once every marker is found.
+    fed: usize,
+}
+impl MarkerDetector {
+    pub fn new(win_sizes: &[usize]) -> Self {
+        Self {
+            run: DistinctRun::new(),
+            pending: win_sizes.to_vec(),
+            fed: 0,
+        }
+    }
+    // Number of bytes fed so far.
+    pub fn offset(&self) -> usize {
+        self.fed
+    }
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<MarkerEvent> {
+        self.fed += chunk.len();
+        let mut events = self.complete(0);
+        for b in chunk {
+            if self.pending.is_empty() {
+                break;
+            }
+            let len = self.run.push(*b);
+            events.extend(self.complete(len));
         }
-        dict.clear();
+
+        events
+    }
+    fn complete(&mut self, len: usize) -> Vec<MarkerEvent> {
+        let offset = self.run.pos;
+        let events = self
+            .pending
+            .iter()
+            .filter(|w| **w <= len)
+            .map(|w| MarkerEvent {
+                win_size: *w,
+                offset,
+            })
+            .collect();
+        self.pending.retain(|w| *w > len);
+
+        events
+    }
+}
+
+// Feeds the reader to a detector until every marker is found or the data ends.
+pub fn detect_in<R: Read>(mut reader: R, win_sizes: &[usize]) -> io::Result<Vec<MarkerEvent>> {
+    let mut detector = MarkerDetector::new(win_sizes);
+    let mut events = detector.feed(&[]);
+    let mut buf = [0u8; 8192];
+    while !detector.is_done() {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        events.extend(detector.feed(&buf[..read]));
+    }
+
+    Ok(events)
+}
+
+// End positions of every window of `win_size` distinct bytes, overlapping
+// windows included.
+pub fn find_all_markers(data: &[u8], win_size: usize) -> Vec<usize> {
+    let mut run = DistinctRun::new();
+
+    data.iter()
+        .enumerate()
+        .filter(|(_, b)| run.push(**b) >= win_size)
+        .map(|(idx, _)| idx + 1)
+        .collect()
+}
+
+// First marker for each of the window sizes, e.g. start-of-packet and
+// start-of-message ones, found in a single pass over the data.
+pub fn find_markers(data: &[u8], win_sizes: &[usize]) -> Vec<Option<usize>> {
+    let events = MarkerDetector::new(win_sizes).feed(data);
+
+    win_sizes
+        .iter()
+        .map(|w| events.iter().find(|e| e.win_size == *w).map(|e| e.offset))
+        .collect()
+}
+
+// Splits the datastream into the payloads following each marker, a payload
+// lasting until the window of the next marker starts. Markers don't overlap
+// and anything before the first one is dropped.
+pub fn split_at_markers(data: &[u8], win_size: usize) -> Vec<&[u8]> {
+    if win_size == 0 {
+        return vec![data];
+    }
+    let mut ends = Vec::new();
+    let mut pos = 0;
+    while let Some(end) = find_marker(&data[pos..], win_size) {
+        pos += end;
+        ends.push(pos);
     }
 
-    0
+    ends.iter()
+        .enumerate()
+        .map(|(idx, start)| {
+            let end = ends.get(idx + 1).map_or(data.len(), |next| next - win_size);
+            &data[*start..end]
+        })
+        .collect()
+}
+
+pub fn do_first_part(file_path: &str) -> usize {
+    let file = read(file_path).unwrap();
+
+    find_marker(&file, 4).unwrap()
+}
+
+pub fn do_sec_part(file_path: &str) -> usize {
+    let file = File::open(file_path).unwrap();
+
+    find_marker_in(file, 14).unwrap().unwrap()
 }
 
 #[cfg(test)]
@@ -42,12 +199,12 @@ mod d6_test {
             "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg".to_owned(),
             "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw".to_owned(),
         ];
-        let answers = vec![5u32, 6, 10, 11];
+        let answers = vec![5usize, 6, 10, 11];
 
         for idx in 0..4 {
-            let res = find_marker(&input[idx], 4);
+            let res = find_marker(input[idx].as_bytes(), 4);
 
-            assert_eq!(res, answers[idx]);
+            assert_eq!(res, Some(answers[idx]));
         }
     }
 
@@ -60,12 +217,89 @@ mod d6_test {
             "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg".to_owned(),
             "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw".to_owned(),
         ];
-        let answers = vec![19u32, 23, 23, 29, 26];
+        let answers = vec![19usize, 23, 23, 29, 26];
 
         for idx in 0..4 {
-            let res = find_marker(&input[idx], 14);
+            let res = find_marker(input[idx].as_bytes(), 14);
+
+            assert_eq!(res, Some(answers[idx]));
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let all_bytes = (0..=255u8).chain(0..=255u8).collect::<Vec<_>>();
+
+        assert_eq!(find_marker(b"aaaa", 2), None);
+        assert_eq!(find_marker(b"abc", 0), Some(0));
+        assert_eq!(find_marker(&all_bytes, 256), Some(256));
+        assert_eq!(find_marker(&all_bytes, 257), None);
+        assert_eq!(find_marker_in(&all_bytes[10..], 256).unwrap(), Some(256));
+        assert_eq!(find_marker_in(&b"abcabcd"[..], 4).unwrap(), Some(7));
+        assert_eq!(find_marker_in(&b"abcabc"[..], 4).unwrap(), None);
+    }
+
+    #[test]
+    fn test_all_markers() {
+        let data = b"mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+
+        assert_eq!(find_all_markers(data, 4)[..3], [7, 8, 9]);
+        assert_eq!(find_all_markers(b"abcabca", 3), vec![3, 4, 5, 6, 7]);
+        assert_eq!(
+            find_markers(data, &[4, 14, 0, 30]),
+            vec![Some(7), Some(19), Some(0), None]
+        );
+        assert_eq!(
+            split_at_markers(b"aaabcdaabbaaefghzzyy", 4),
+            vec![&b"aabba"[..], &b"hzzyy"[..]]
+        );
+        assert_eq!(
+            split_at_markers(b"aaabcdaaaaxyzwbb", 4),
+            vec![&b"aaa"[..], &b"wbb"[..]]
+        );
+        assert!(split_at_markers(b"aaaa", 2).is_empty());
+    }
+
+    #[test]
+    fn test_detector() {
+        let input = [
+            "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+            "bvwbjplbgvbhsrlpgdmjqwftvncz",
+            "nppdvjthqldpwncqszvftbrmjlhg",
+            "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+            "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
+        ];
+        let answers = [(7, 19), (5, 23), (6, 23), (10, 29), (11, 26)];
+
+        for (data, (packet, message)) in input.iter().map(|i| i.as_bytes()).zip(answers) {
+            let expected = vec![
+                MarkerEvent {
+                    win_size: 4,
+                    offset: packet,
+                },
+                MarkerEvent {
+                    win_size: 14,
+                    offset: message,
+                },
+            ];
+            for split in 0..=data.len() {
+                let mut detector = MarkerDetector::new(&[4, 14]);
+                let (head, tail) = data.split_at(split);
+                let mut events = detector.feed(head);
+                assert!(events.iter().all(|e| e.offset <= split));
+                assert_eq!(detector.offset(), split);
+                events.extend(detector.feed(tail));
+                assert_eq!(events, expected);
+            }
 
-            assert_eq!(res, answers[idx]);
+            let mut detector = MarkerDetector::new(&[14, 4]);
+            let events = data
+                .chunks(1)
+                .flat_map(|c| detector.feed(c))
+                .collect::<Vec<_>>();
+            assert_eq!(events, expected);
+            assert_eq!(detector.offset(), data.len());
+            assert_eq!(detect_in(data, &[4, 14]).unwrap(), expected);
         }
     }
 }
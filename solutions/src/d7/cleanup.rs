@@ -0,0 +1,196 @@
+use super::{FileSystem, NodeId};
+
+// Disk the filesystem lives on, sizes use the same unit as the transcript.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disk {
+    pub capacity: u64,
+    // Free space needed, e.g. to run an update.
+    pub required: u64,
+}
+
+impl Disk {
+    pub const PUZZLE: Disk = Disk {
+        capacity: 70_000_000,
+        required: 30_000_000,
+    };
+
+    // Space that has to be freed so that `required` is free afterwards, the
+    // tree may already be bigger than the disk.
+    pub fn to_free(&self, fs: &FileSystem) -> Result<u64, String> {
+        if self.required > self.capacity {
+            return Err(format!(
+                "{} required but the disk only holds {}",
+                self.required, self.capacity
+            ));
+        }
+        let used = fs.size(FileSystem::ROOT);
+        let needed = used
+            .checked_add(self.required)
+            .ok_or_else(|| format!("{used} used plus {} required overflows", self.required))?;
+
+        Ok(needed.saturating_sub(self.capacity))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deletable {
+    Dirs,
+    Files,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CleanupPlan {
+    pub to_free: u64,
+    pub freed: u64,
+    // Whether the search finished, otherwise `freed` is the best total found
+    // within the step budget.
+    pub exact: bool,
+    // Sizes and paths of the nodes to delete, none of them inside another.
+    pub deleted: Vec<(u64, String)>,
+}
+
+struct Item {
+    id: NodeId,
+    size: u64,
+    candidate: bool,
+    // Walk position right after the subtree of this node.
+    subtree_end: usize,
+    // Total size of the files from this position of the walk onwards, upper
+    // bound of what can still be freed.
+    remaining: u64,
+}
+
+// Candidates looked at before the search settles for the best plan so far.
+const STEP_BUDGET: usize = 1_000_000;
+
+// Branch and bound over the pre-order walk: each candidate is either deleted,
+// skipping its subtree, or kept, descending into it. Chosen nodes never
+// overlap, so sums stay below the root size and can't overflow.
+//
+// Finding the exact minimum is a subset sum problem and takes exponential
+// time on unlucky inputs, so the search stops after `STEP_BUDGET` steps. The
+// first descent deletes candidates in walk order until enough is freed, so a
+// plan is always found within O(n) steps before the budget applies.
+struct Search {
+    items: Vec<Item>,
+    to_free: u64,
+    chosen: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+    steps: usize,
+}
+
+impl Search {
+    fn cut_off(&self) -> bool {
+        self.best.is_some() && self.steps >= STEP_BUDGET
+    }
+    fn run(&mut self, start: usize, freed: u64) {
+        for idx in start..self.items.len() {
+            if self.cut_off() {
+                return;
+            }
+            self.steps += 1;
+            let Item {
+                size,
+                candidate,
+                subtree_end,
+                remaining,
+                ..
+            } = self.items[idx];
+            if freed + remaining < self.to_free
+                || self.best.as_ref().is_some_and(|b| b.0 == self.to_free)
+            {
+                return;
+            }
+            let total = freed + size;
+            if !candidate || size == 0 || self.best.as_ref().is_some_and(|b| total >= b.0) {
+                continue;
+            }
+            self.chosen.push(idx);
+            if total >= self.to_free {
+                self.best = Some((total, self.chosen.clone()));
+            } else {
+                self.run(subtree_end, total);
+            }
+            self.chosen.pop();
+        }
+    }
+}
+
+impl FileSystem {
+    // Smallest single directory freeing enough space, root included.
+    pub fn smallest_dir_to_delete(&self, disk: &Disk) -> Result<NodeId, String> {
+        let to_free = disk.to_free(self)?;
+
+        self.query()
+            .dirs()
+            .size(to_free..)
+            .nodes()
+            .min_by_key(|id| self.size(*id))
+            .ok_or_else(|| format!("no directory frees {to_free}"))
+    }
+
+    // Set of directories or files, none nested in another, with the smallest
+    // total size still freeing enough space.
+    pub fn plan_cleanup(&self, disk: &Disk, deletable: Deletable) -> Result<CleanupPlan, String> {
+        let to_free = disk.to_free(self)?;
+        let mut items = self
+            .walk(FileSystem::ROOT)
+            .map(|id| Item {
+                id,
+                size: self.size(id),
+                candidate: self.node(id).is_dir() == (deletable == Deletable::Dirs),
+                subtree_end: 0,
+                remaining: 0,
+            })
+            .collect::<Vec<_>>();
+        // Backwards over the walk children come before their parent, so
+        // subtree lengths and remaining sizes are known in a single pass.
+        let mut subtree_len = vec![1; self.len()];
+        let mut remaining = 0;
+        for (pos, item) in items.iter_mut().enumerate().rev() {
+            let node = self.node(item.id);
+            if !node.is_dir() {
+                remaining += item.size;
+            }
+            item.remaining = remaining;
+            item.subtree_end = pos + subtree_len[item.id];
+            if let Some(parent) = node.parent {
+                subtree_len[parent] += subtree_len[item.id];
+            }
+        }
+
+        let mut search = Search {
+            items,
+            to_free,
+            chosen: Vec::new(),
+            best: (to_free == 0).then(|| (0, Vec::new())),
+            steps: 0,
+        };
+        // A single node is a good first bound to prune with.
+        search.best = search.best.or_else(|| {
+            let items = &search.items;
+            (0..items.len())
+                .filter(|i| items[*i].candidate && items[*i].size >= to_free)
+                .min_by_key(|i| items[*i].size)
+                .map(|i| (items[i].size, vec![i]))
+        });
+        search.run(0, 0);
+        let exact = !search.cut_off();
+
+        let (freed, chosen) = search
+            .best
+            .ok_or_else(|| format!("deleting every {deletable:?} doesn't free {to_free}"))?;
+        let deleted = chosen
+            .into_iter()
+            .map(|i| search.items[i].id)
+            .map(|id| (self.size(id), self.path(id)))
+            .collect();
+
+        Ok(CleanupPlan {
+            to_free,
+            freed,
+            exact,
+            deleted,
+        })
+    }
+}
@@ -0,0 +1,208 @@
+pub type NodeId = usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsNodeType {
+    Root,
+    Dir(String),
+    File(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsNode {
+    pub node_type: FsNodeType,
+    // Size of a file, or the total size of everything inside a directory.
+    pub size: u64,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+}
+
+impl FsNode {
+    pub fn name(&self) -> &str {
+        match &self.node_type {
+            FsNodeType::Root => "/",
+            FsNodeType::Dir(name) | FsNodeType::File(name) => name,
+        }
+    }
+    pub fn is_dir(&self) -> bool {
+        !matches!(self.node_type, FsNodeType::File(_))
+    }
+}
+
+// Filesystem tree stored in an arena, nodes refer to each other by index and
+// the root is always the first node. Directory sizes are kept up to date as
+// files are added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSystem {
+    nodes: Vec<FsNode>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![FsNode {
+                node_type: FsNodeType::Root,
+                size: 0,
+                parent: None,
+                children: Vec::new(),
+            }],
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+    pub fn node(&self, id: NodeId) -> &FsNode {
+        &self.nodes[id]
+    }
+    pub fn size(&self, id: NodeId) -> u64 {
+        self.nodes[id].size
+    }
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent
+    }
+    pub fn list(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id].children
+    }
+    pub fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        self.nodes[id]
+            .children
+            .iter()
+            .copied()
+            .find(|c| self.nodes[*c].name() == name)
+    }
+
+    // Names a transcript can express and that stay inside their directory
+    // once put on a real disk.
+    pub fn is_valid_name(name: &str) -> bool {
+        !(name.is_empty()
+            || name == "."
+            || name == ".."
+            || name.contains(|c: char| c == '/' || c.is_whitespace()))
+    }
+
+    // Returns the existing directory with that name or creates a new one.
+    pub fn add_dir(&mut self, parent: NodeId, name: &str) -> Result<NodeId, String> {
+        self.check_name(parent, name)?;
+        match self.child(parent, name) {
+            Some(existing) if self.nodes[existing].is_dir() => Ok(existing),
+            Some(existing) => Err(format!("{} is a file", self.path(existing))),
+            None => Ok(self.push(parent, FsNodeType::Dir(name.to_string()), 0)),
+        }
+    }
+    // Adds a file, listing an already known file again is fine as long as its
+    // size hasn't changed.
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: u64) -> Result<NodeId, String> {
+        self.check_name(parent, name)?;
+        if let Some(existing) = self.child(parent, name) {
+            let node = &self.nodes[existing];
+            return match node.node_type {
+                FsNodeType::File(_) if node.size == size => Ok(existing),
+                FsNodeType::File(_) => Err(format!(
+                    "{} listed with size {size}, previously {}",
+                    self.path(existing),
+                    node.size
+                )),
+                _ => Err(format!("{} is a directory", self.path(existing))),
+            };
+        }
+        // Every directory is at most as big as the root, so checking it is enough.
+        let root = self.nodes[Self::ROOT].size;
+        if root.checked_add(size).is_none() {
+            return Err(format!(
+                "adding {size} bytes overflows the total size {root}"
+            ));
+        }
+        let id = self.push(parent, FsNodeType::File(name.to_string()), size);
+        let mut dir = Some(parent);
+        while let Some(d) = dir {
+            self.nodes[d].size += size;
+            dir = self.nodes[d].parent;
+        }
+
+        Ok(id)
+    }
+    fn check_name(&self, parent: NodeId, name: &str) -> Result<(), String> {
+        match Self::is_valid_name(name) {
+            true => Ok(()),
+            false => Err(format!("{}: invalid name {name:?}", self.path(parent))),
+        }
+    }
+    fn push(&mut self, parent: NodeId, node_type: FsNodeType, size: u64) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(FsNode {
+            node_type,
+            size,
+            parent: Some(parent),
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(id);
+
+        id
+    }
+
+    // Resolves an absolute path, or a path relative to `from`, supporting
+    // `.` and `..` components.
+    pub fn resolve(&self, from: NodeId, path: &str) -> Option<NodeId> {
+        let mut cur = if path.starts_with('/') {
+            Self::ROOT
+        } else {
+            from
+        };
+        for part in path.split('/').filter(|p| !p.is_empty()) {
+            cur = match part {
+                "." => cur,
+                ".." => self.parent(cur).unwrap_or(Self::ROOT),
+                name => self.child(cur, name)?,
+            };
+        }
+
+        Some(cur)
+    }
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.starts_with('/')
+            .then(|| self.resolve(Self::ROOT, path))
+            .flatten()
+    }
+    pub fn path(&self, id: NodeId) -> String {
+        let mut parts = Vec::new();
+        let mut cur = id;
+        while let Some(parent) = self.parent(cur) {
+            parts.push(self.nodes[cur].name());
+            cur = parent;
+        }
+        parts.reverse();
+
+        format!("/{}", parts.join("/"))
+    }
+    pub fn depth(&self, id: NodeId) -> usize {
+        std::iter::successors(self.parent(id), |p| self.parent(*p)).count()
+    }
+
+    // Pre-order walk over the subtree rooted at `id`, `id` included.
+    pub fn walk(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let mut stack = vec![id];
+
+        std::iter::from_fn(move || {
+            let cur = stack.pop()?;
+            stack.extend(self.nodes[cur].children.iter().rev());
+
+            Some(cur)
+        })
+    }
+    pub fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.walk(Self::ROOT).filter(|id| self.nodes[*id].is_dir())
+    }
+    pub fn files(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.walk(Self::ROOT).filter(|id| !self.nodes[*id].is_dir())
+    }
+}
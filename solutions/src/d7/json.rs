@@ -0,0 +1,263 @@
+use std::fmt::Write;
+
+use super::{FileSystem, NodeId};
+
+// The small subset of JSON the tree export needs, numbers are sizes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Json {
+    Object(Vec<(String, Json)>),
+    Array(Vec<Json>),
+    String(String),
+    Number(u64),
+}
+
+impl Json {
+    fn field(&self, key: &str) -> Result<&Json, String> {
+        let Json::Object(fields) = self else {
+            return Err(format!("expected an object, found {self:?}"));
+        };
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .ok_or_else(|| format!("missing field {key:?}"))
+    }
+    fn as_str(&self) -> Result<&str, String> {
+        match self {
+            Json::String(s) => Ok(s),
+            _ => Err(format!("expected a string, found {self:?}")),
+        }
+    }
+    fn as_u64(&self) -> Result<u64, String> {
+        match self {
+            Json::Number(n) => Ok(*n),
+            _ => Err(format!("expected a number, found {self:?}")),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_ws();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((pos, c)) => Err(format!("offset {pos}: expected {expected:?}, found {c:?}")),
+            None => Err(format!("expected {expected:?}, found end of input")),
+        }
+    }
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_ws();
+        match self.chars.peek().copied() {
+            Some((_, '{')) => self.object(),
+            Some((_, '[')) => self.array(),
+            Some((_, '"')) => self.string().map(Json::String),
+            Some((_, '0'..='9')) => self.number(),
+            Some((pos, c)) => Err(format!("offset {pos}: unexpected {c:?}")),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+    // Comma separated items up to the closing delimiter.
+    fn items(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.skip_ws();
+        if self.chars.next_if(|(_, c)| *c == close).is_some() {
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_ws();
+            match self.chars.next() {
+                Some((_, ',')) => (),
+                Some((_, c)) if c == close => return Ok(()),
+                Some((pos, c)) => return Err(format!("offset {pos}: unexpected {c:?}")),
+                None => return Err("unexpected end of input".to_string()),
+            }
+        }
+    }
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.items('}', |p| {
+            p.skip_ws();
+            let key = p.string()?;
+            p.expect(':')?;
+            fields.push((key, p.value()?));
+            Ok(())
+        })?;
+
+        Ok(Json::Object(fields))
+    }
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.items(']', |p| {
+            values.push(p.value()?);
+            Ok(())
+        })?;
+
+        Ok(Json::Array(values))
+    }
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let (pos, c) = self.chars.next().ok_or("unterminated string")?;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let (_, escaped) = self.chars.next().ok_or("unterminated string")?;
+                    s.push(match escaped {
+                        '"' | '\\' | '/' => escaped,
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let hex = (0..4)
+                                .filter_map(|_| self.chars.next().map(|(_, c)| c))
+                                .collect::<String>();
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("offset {pos}: bad escape \\u{hex}"))?
+                        }
+                        _ => return Err(format!("offset {pos}: bad escape \\{escaped}")),
+                    });
+                }
+                _ => s.push(c),
+            }
+        }
+    }
+    fn number(&mut self) -> Result<Json, String> {
+        let mut digits = String::new();
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            digits.push(c);
+        }
+        digits
+            .parse()
+            .map(Json::Number)
+            .map_err(|e| format!("bad number {digits:?}: {e}"))
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
+
+impl FileSystem {
+    // Pretty printed JSON document, one field per line so exports diff well.
+    // Every node has a name, a type and a size, directories list their
+    // children in order.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(Self::ROOT, 0, &mut out);
+        out.push('\n');
+
+        out
+    }
+    fn write_json(&self, id: NodeId, depth: usize, out: &mut String) {
+        let node = self.node(id);
+        let pad = "  ".repeat(depth + 1);
+        let kind = if node.is_dir() { "dir" } else { "file" };
+        out.push_str("{\n");
+        writeln!(out, "{pad}\"name\": {},", escape(node.name())).unwrap();
+        writeln!(out, "{pad}\"type\": \"{kind}\",").unwrap();
+        write!(out, "{pad}\"size\": {}", node.size).unwrap();
+        if node.is_dir() {
+            write!(out, ",\n{pad}\"children\": [").unwrap();
+            for (idx, child) in node.children.iter().enumerate() {
+                out.push_str(if idx == 0 { "\n" } else { ",\n" });
+                out.push_str(&pad);
+                out.push_str("  ");
+                self.write_json(*child, depth + 2, out);
+            }
+            if !node.children.is_empty() {
+                write!(out, "\n{pad}").unwrap();
+            }
+            out.push(']');
+        }
+        write!(out, "\n{}}}", "  ".repeat(depth)).unwrap();
+    }
+
+    // Reads a document written by `to_json`. Directory sizes are checked
+    // against their contents.
+    pub fn from_json(json: &str) -> Result<FileSystem, String> {
+        let mut parser = Parser {
+            chars: json.char_indices().peekable(),
+        };
+        let root = parser.value()?;
+        parser.skip_ws();
+        if let Some((pos, _)) = parser.chars.next() {
+            return Err(format!("offset {pos}: trailing data"));
+        }
+        if root.field("type")?.as_str()? != "dir" {
+            return Err("the root must be a directory".to_string());
+        }
+
+        let mut fs = FileSystem::new();
+        fs.import_children(Self::ROOT, &root)?;
+        fs.check_size(Self::ROOT, &root)?;
+
+        Ok(fs)
+    }
+    fn import_children(&mut self, dir: NodeId, json: &Json) -> Result<(), String> {
+        let Json::Array(children) = json.field("children")? else {
+            return Err(format!("{}: children must be an array", self.path(dir)));
+        };
+        for child in children {
+            let name = child.field("name")?.as_str()?;
+            if self.child(dir, name).is_some() {
+                return Err(format!("{}: duplicate name {name:?}", self.path(dir)));
+            }
+            let size = child.field("size")?.as_u64()?;
+            match child.field("type")?.as_str()? {
+                "file" => {
+                    self.add_file(dir, name, size)?;
+                }
+                "dir" => {
+                    let id = self.add_dir(dir, name)?;
+                    self.import_children(id, child)?;
+                    self.check_size(id, child)?;
+                }
+                other => return Err(format!("{}: unknown type {other:?}", self.path(dir))),
+            }
+        }
+
+        Ok(())
+    }
+    fn check_size(&self, id: NodeId, json: &Json) -> Result<(), String> {
+        let expected = json.field("size")?.as_u64()?;
+        match self.size(id) {
+            size if size == expected => Ok(()),
+            size => Err(format!(
+                "{}: size {expected} but its contents add up to {size}",
+                self.path(id)
+            )),
+        }
+    }
+}
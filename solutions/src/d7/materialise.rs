@@ -0,0 +1,91 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use super::{FileSystem, NodeId};
+
+// How files get their size when written to disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileContents {
+    // Holes of the listed size, nothing is actually written.
+    Sparse,
+    // Real zeros, files larger than the cap are truncated to it.
+    ZeroFilled { cap: u64 },
+}
+
+fn at_path(path: &Path) -> impl Fn(io::Error) -> String + '_ {
+    move |e| format!("{}: {e}", path.display())
+}
+
+impl FileSystem {
+    // Creates the tree under `target`, which is created too if missing.
+    pub fn materialise(&self, target: &Path, contents: FileContents) -> Result<(), String> {
+        fs::create_dir_all(target).map_err(at_path(target))?;
+        self.materialise_dir(Self::ROOT, target, contents)
+    }
+    fn materialise_dir(
+        &self,
+        dir: NodeId,
+        path: &Path,
+        contents: FileContents,
+    ) -> Result<(), String> {
+        for child in self.list(dir).iter().copied() {
+            let node = self.node(child);
+            let child_path = path.join(node.name());
+            if node.is_dir() {
+                fs::create_dir_all(&child_path).map_err(at_path(&child_path))?;
+                self.materialise_dir(child, &child_path, contents)?;
+                continue;
+            }
+            let mut file = File::create(&child_path).map_err(at_path(&child_path))?;
+            match contents {
+                FileContents::Sparse => file.set_len(node.size),
+                FileContents::ZeroFilled { cap } => {
+                    io::copy(&mut io::repeat(0).take(node.size.min(cap)), &mut file).map(|_| ())
+                }
+            }
+            .map_err(at_path(&child_path))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Reads a real directory into a tree, entries sorted by name. Symlinks and
+// other special files are skipped, names a transcript can't hold are errors.
+pub fn scan(root: &Path) -> Result<FileSystem, String> {
+    let mut fs = FileSystem::new();
+    scan_dir(&mut fs, FileSystem::ROOT, root)?;
+
+    Ok(fs)
+}
+
+fn scan_dir(fs: &mut FileSystem, dir: NodeId, path: &Path) -> Result<(), String> {
+    let mut entries = fs::read_dir(path)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(at_path(path))?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let entry_path = entry.path();
+        let name = entry.file_name();
+        let name = name
+            .to_str()
+            .filter(|name| FileSystem::is_valid_name(name))
+            .ok_or_else(|| format!("{}: unsupported name", entry_path.display()))?;
+        let meta = fs::symlink_metadata(&entry_path).map_err(at_path(&entry_path))?;
+        if meta.is_dir() {
+            let id = fs.add_dir(dir, name)?;
+            scan_dir(fs, id, &entry_path)?;
+        } else if meta.is_file() {
+            fs.add_file(dir, name, meta.len())?;
+        }
+    }
+
+    Ok(())
+}
+
+// Transcript of a real directory, as `parse` expects it.
+pub fn scan_transcript(root: &Path) -> Result<String, String> {
+    scan(root).map(|fs| fs.transcript())
+}
@@ -1,5 +1,19 @@
 use std::fs::read_to_string;
 
+mod cleanup;
+mod fs;
+mod json;
+mod materialise;
+mod query;
+mod report;
+mod transcript;
+
+pub use cleanup::{CleanupPlan, Deletable, Disk};
+pub use fs::{FileSystem, FsNode, FsNodeType, NodeId};
+pub use materialise::{scan, scan_transcript, FileContents};
+pub use query::Query;
+pub use report::format_listing;
+
 #[derive(Debug)]
 enum Command<'a> {
     Cd(&'a str),
@@ -8,223 +22,489 @@ enum Command<'a> {
 #[derive(Debug)]
 enum LineType<'a> {
     Command(Command<'a>),
-    InfoFile((u32, &'a str)),
+    InfoFile((u64, &'a str)),
     InfoDir(&'a str),
 }
-#[derive(Debug)]
-enum FsNodeType<'a> {
-    Root,
-    Dir(&'a str),
-    File(&'a str),
-}
 
-#[derive(Debug)]
-struct FsNode<'a> {
-    node_type: FsNodeType<'a>,
-    size: u32,
-    children: Option<Vec<Box<FsNode<'a>>>>,
+// Replays a terminal transcript. `cd /` may appear anywhere, directories can
+// be visited and listed any number of times and `dir` entries create empty
+// directories. Listings contradicting earlier ones are reported as errors.
+pub fn parse(content: &str) -> Result<FileSystem, String> {
+    let mut fs = FileSystem::new();
+    let mut cwd = FileSystem::ROOT;
+    let mut listing = false;
+
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let at_line = |e: String| format!("Line {}: {e}", idx + 1);
+        let line_type = get_line_type(line).map_err(at_line)?;
+        match line_type {
+            LineType::Command(_) => listing = false,
+            _ if !listing => return Err(at_line("output without `$ ls`".to_string())),
+            _ => (),
+        }
+        match line_type {
+            LineType::InfoFile((fsize, fname)) => {
+                fs.add_file(cwd, fname, fsize).map_err(at_line)?;
+            }
+            LineType::InfoDir(dir) => {
+                fs.add_dir(cwd, dir).map_err(at_line)?;
+            }
+            LineType::Command(Command::Cd("/")) => cwd = FileSystem::ROOT,
+            LineType::Command(Command::Cd("..")) => {
+                cwd = fs.parent(cwd).unwrap_or(FileSystem::ROOT);
+            }
+            LineType::Command(Command::Cd(dir)) => cwd = fs.add_dir(cwd, dir).map_err(at_line)?,
+            LineType::Command(Command::Ls) => listing = true,
+        }
+    }
+
+    Ok(fs)
 }
 
-impl<'a> FsNode<'a> {
-    fn new() -> Box<FsNode<'a>> {
-        Box::new(Self {
-            node_type: FsNodeType::Root,
-            size: 0,
-            children: Some(Vec::new()),
-        })
+fn get_line_type(line: &str) -> Result<LineType<'_>, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+
+    match words[..] {
+        ["$", "ls"] => Ok(LineType::Command(Command::Ls)),
+        ["$", "cd", dir] => Ok(LineType::Command(Command::Cd(dir))),
+        ["$", ..] => Err(format!("unknown command {line:?}")),
+        ["dir", name] => Ok(LineType::InfoDir(name)),
+        [size, name] => size
+            .parse::<u64>()
+            .map(|size| LineType::InfoFile((size, name)))
+            .map_err(|e| format!("bad file size {size:?}: {e}")),
+        _ => Err(format!("unrecognized line {line:?}")),
     }
-    fn with_type(node_type: FsNodeType<'a>) -> Box<FsNode<'a>> {
-        Box::new(Self {
-            node_type,
-            size: 0,
-            children: Some(Vec::new()),
-        })
+}
+
+pub fn do_first_part(fpath: &str) -> u64 {
+    let fs = parse(&read_to_string(fpath).unwrap()).unwrap();
+
+    fs.query().dirs().size(..=100_000).sum().unwrap()
+}
+
+pub fn do_sec_part(fpath: &str) -> u64 {
+    let fs = parse(&read_to_string(fpath).unwrap()).unwrap();
+    let to_free = Disk::PUZZLE.to_free(&fs).unwrap();
+
+    fs.query().dirs().size(to_free..).min().unwrap()
+}
+
+pub fn plan(fpath: &str, disk: &Disk, deletable: Deletable) -> Result<CleanupPlan, String> {
+    let content = read_to_string(fpath).map_err(|e| e.to_string())?;
+
+    parse(&content)?.plan_cleanup(disk, deletable)
+}
+
+#[cfg(test)]
+mod d7_test {
+    use super::*;
+    use crate::test_rng::TestRng;
+
+    #[test]
+    fn test_first() {
+        let res = do_first_part("./src/d7/test.txt");
+
+        assert_eq!(res, 95437);
     }
-    fn new_file(name: &'a str, size: u32) -> Box<FsNode<'a>> {
-        Box::new(Self {
-            node_type: FsNodeType::File(name),
-            size,
-            children: None,
-        })
+
+    #[test]
+    fn test_sec() {
+        let res = do_sec_part("./src/d7/test.txt");
+
+        assert_eq!(res, 24933642);
     }
-    fn add_child(&mut self, node: Box<FsNode<'a>>) -> &mut Self {
-        self.children.as_mut().map(|c| c.push(node));
 
-        self
+    #[test]
+    fn test_fs() {
+        let fs = parse(&read_to_string("./src/d7/test.txt").unwrap()).unwrap();
+        let e = fs.lookup("/a/e").unwrap();
+
+        assert_eq!(fs.size(e), 584);
+        assert_eq!(fs.path(e), "/a/e");
+        assert_eq!(fs.depth(e), 2);
+        assert_eq!(fs.resolve(e, "../../d/k"), fs.lookup("/d/k"));
+        assert_eq!(fs.resolve(e, "./i").map(|i| fs.node(i).name()), Some("i"));
+        assert_eq!(fs.lookup("/a/x"), None);
+        assert_eq!(fs.lookup("a"), None);
+        assert_eq!(
+            fs.list(FileSystem::ROOT)
+                .iter()
+                .map(|c| fs.node(*c).name())
+                .collect::<Vec<_>>(),
+            vec!["a", "b.txt", "c.dat", "d"]
+        );
+        assert_eq!(
+            fs.walk(fs.lookup("/a").unwrap())
+                .map(|id| fs.path(id))
+                .collect::<Vec<_>>(),
+            vec!["/a", "/a/e", "/a/e/i", "/a/f", "/a/g", "/a/h.lst"]
+        );
+        assert_eq!(fs.dirs().count(), 4);
+        assert_eq!(fs.size(FileSystem::ROOT), 48381165);
     }
 
-    fn calc_size(&mut self) -> &mut Self {
-        if self.children.is_none() {
-            return self;
-        }
-        let children: &mut Vec<Box<FsNode>> = self.children.as_mut().unwrap();
+    #[test]
+    fn test_messy_transcript() {
+        let transcript = "\
+$ cd a
+$ ls
+10 x
+dir empty
+$ cd /
+$ ls
+dir a
+5 y
+$ cd a
+$ ls
+10 x
+$ cd empty
+$ cd ..
+$ cd b
+$ cd /
+$ ls
+5 y
+";
+        let fs = parse(transcript).unwrap();
+        let paths = fs.walk(FileSystem::ROOT).map(|id| fs.path(id));
 
-        self.size = get_size(children);
+        assert_eq!(
+            paths.collect::<Vec<_>>(),
+            vec!["/", "/a", "/a/x", "/a/empty", "/a/b", "/y"]
+        );
+        assert_eq!(fs.size(FileSystem::ROOT), 15);
+        assert_eq!(fs.size(fs.lookup("/a/empty").unwrap()), 0);
 
-        self
+        let err = parse("$ cd /\n$ ls\n1 f\n$ ls\n2 f\n").unwrap_err();
+        assert_eq!(err, "Line 5: /f listed with size 2, previously 1");
+        assert!(parse("$ ls\n1 f\n$ cd f\n").is_err());
+        assert!(parse("$ ls\ndir f\n1 f\n").is_err());
+        assert!(parse("$ cd /\n1 f\n").is_err());
+        assert!(parse("$ rm -rf /\n").is_err());
+        assert!(parse("$ ls\nbig f\n").is_err());
+        assert_eq!(
+            parse("$ ls\ndir ..\n").unwrap_err(),
+            "Line 2: /: invalid name \"..\""
+        );
+        assert!(parse("$ cd a/b\n").is_err());
+        assert!(parse("$ cd .\n").is_err());
     }
 
-    fn get_dir_sum_size_le_than(&self, th_size: u32) -> u32 {
-        if self.children.is_none() {
-            return self.size;
-        }
-        let mut total_size = 0;
-        for child in self.children.as_ref().unwrap() {
-            match child.node_type {
-                FsNodeType::Dir(_) => {
-                    if child.size < th_size {
-                        total_size += child.size;
-                    }
-                    total_size += child.get_dir_sum_size_le_than(th_size);
-                }
-                _ => (),
-            }
-        }
+    #[test]
+    fn test_reports() {
+        let fs = parse(&read_to_string("./src/d7/test.txt").unwrap()).unwrap();
 
-        total_size
+        assert_eq!(
+            fs.tree(),
+            "\
+- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+"
+        );
+        assert_eq!(
+            format_listing(&fs.du()),
+            "48381165\t/\n24933642\t/d\n94853\t/a\n584\t/a/e\n"
+        );
+        assert_eq!(
+            fs.largest_files(2),
+            vec![
+                (14848514, "/b.txt".to_string()),
+                (8504156, "/c.dat".to_string())
+            ]
+        );
+        assert_eq!(fs.largest_files(20).len(), 10);
     }
 
-    fn get_closest_dir_size_greater_than(&self, th_size: u32, old_val: u32) -> u32 {
-        let mut result = old_val;
-        if self.children.is_none() {
-            return 0;
-        }
-        for child in self.children.as_ref().unwrap() {
-            match child.node_type {
-                FsNodeType::Dir(_) => {
-                    if child.size < th_size {
-                        continue;
-                    }
-                    let child_closest =
-                        child.get_closest_dir_size_greater_than(th_size, child.size);
-                    if child_closest < result && child_closest > th_size {
-                        result = child_closest;
-                    }
-                }
-                _ => (),
-            }
-        }
+    #[test]
+    fn test_cleanup() {
+        let fs = parse(&read_to_string("./src/d7/test.txt").unwrap()).unwrap();
+        let single = fs.smallest_dir_to_delete(&Disk::PUZZLE).unwrap();
+        assert_eq!(fs.path(single), "/d");
+
+        let plan = fs.plan_cleanup(&Disk::PUZZLE, Deletable::Files).unwrap();
+        assert_eq!(plan.to_free, 8381165);
+        assert_eq!(plan.freed, 8504156);
+        assert_eq!(plan.deleted, vec![(8504156, "/c.dat".to_string())]);
+
+        assert!(plan.exact);
+        let plan = fs.plan_cleanup(&Disk::PUZZLE, Deletable::Dirs).unwrap();
+        assert_eq!(plan.freed, 24933642);
+        assert_eq!(plan.deleted, vec![(24933642, "/d".to_string())]);
 
-        result
+        let disk = Disk {
+            capacity: 48381165,
+            required: 25_000_000,
+        };
+        let plan = fs.plan_cleanup(&disk, Deletable::Dirs).unwrap();
+        assert_eq!(
+            plan.deleted,
+            vec![(94853, "/a".to_string()), (24933642, "/d".to_string())]
+        );
+        let sizes = fs.files().map(|id| fs.size(id)).collect::<Vec<_>>();
+        let brute_force = (0..1u32 << sizes.len())
+            .map(|set| {
+                let chosen = sizes.iter().enumerate().filter(|(i, _)| set & 1 << i != 0);
+                chosen.map(|(_, size)| size).sum::<u64>()
+            })
+            .filter(|sum| *sum >= 25_000_000)
+            .min();
+        let plan = fs.plan_cleanup(&disk, Deletable::Files).unwrap();
+        assert_eq!(Some(plan.freed), brute_force);
+        assert_eq!(plan.deleted.iter().map(|d| d.0).sum::<u64>(), plan.freed);
+
+        let roomy = Disk {
+            capacity: 100_000_000,
+            required: 1,
+        };
+        assert_eq!(fs.plan_cleanup(&roomy, Deletable::Files).unwrap().freed, 0);
+        let tiny = Disk {
+            capacity: 1,
+            required: 1,
+        };
+        // Only deleting everything gets the tree onto a 1 byte disk.
+        let plan = fs.plan_cleanup(&tiny, Deletable::Files).unwrap();
+        assert_eq!(plan.to_free, 48381165);
+        assert_eq!(plan.freed, 48381165);
+        assert_eq!(plan.deleted.len(), 10);
+        let overfull = Disk {
+            capacity: 48381165 - 50,
+            required: 30,
+        };
+        assert_eq!(overfull.to_free(&fs), Ok(80));
+        let impossible = Disk {
+            capacity: 1,
+            required: 2,
+        };
+        assert_eq!(
+            fs.plan_cleanup(&impossible, Deletable::Dirs).unwrap_err(),
+            "2 required but the disk only holds 1"
+        );
+        assert!(fs.smallest_dir_to_delete(&impossible).is_err());
+        let no_files = Disk {
+            capacity: 10,
+            required: 5,
+        };
+        assert!(FileSystem::new()
+            .plan_cleanup(&no_files, Deletable::Files)
+            .is_ok());
     }
-}
-fn get_size(children: &mut Vec<Box<FsNode>>) -> u32 {
-    let mut total_size = 0u32;
-    for child in children {
-        match child.node_type {
-            FsNodeType::File(_) => {
-                total_size += child.size;
-            }
-            FsNodeType::Dir(_) => {
-                let dir_size = get_size(child.children.as_mut().unwrap());
-                child.size = dir_size;
-                total_size += dir_size;
-            }
-            _ => (),
-        }
+
+    #[test]
+    fn test_large_sizes() {
+        let transcript = "$ ls\n5000000000 a\ndir d\n$ cd d\n$ ls\n4000000000 b\n";
+        let fs = parse(transcript).unwrap();
+        assert_eq!(fs.size(FileSystem::ROOT), 9_000_000_000);
+
+        let disk = Disk {
+            capacity: 10_000_000_000,
+            required: 5_000_000_000,
+        };
+        let dir = fs.smallest_dir_to_delete(&disk).unwrap();
+        assert_eq!(fs.path(dir), "/d");
+
+        let err = parse("$ ls\n18446744073709551615 a\n1 b\n").unwrap_err();
+        assert_eq!(
+            err,
+            "Line 3: adding 1 bytes overflows the total size 18446744073709551615"
+        );
+        assert!(parse("$ ls\n18446744073709551616 a\n").is_err());
     }
 
-    total_size
-}
-fn parse<'a, 'b>(
-    content: &'a str,
-    parent: &'b mut Box<FsNode<'a>>,
-) -> (&'b mut Box<FsNode<'a>>, &'a str) {
-    let mut chunk = content;
-    let mut left_idx = 0usize;
-    let mut chars = chunk.chars();
-    let mut chunk_size = chunk.len();
-
-    while let Some(ch) = chars.next() {
-        if ch == '\n' {
-            let right_idx = chunk_size - chars.as_str().len();
-
-            let line = &chunk[left_idx..right_idx];
-            left_idx = right_idx;
-
-            match get_line_type(line) {
-                LineType::InfoFile((fsize, fname)) => {
-                    parent.add_child(FsNode::new_file(fname, fsize));
-                }
-                LineType::Command(cmd) => match cmd {
-                    Command::Ls => (),
-                    Command::Cd(dir) => {
-                        if dir == ".." {
-                            return (parent, chars.as_str());
-                        }
-
-                        let mut new_dir = FsNode::with_type(FsNodeType::Dir(dir));
-                        let (_, rest) = parse(chars.as_str(), &mut new_dir);
-
-                        chunk = rest;
-                        chars = rest.chars();
-                        left_idx = 0;
-                        chunk_size = rest.len();
-                        parent.add_child(new_dir);
-                    }
-                },
-                _ => (),
-            };
+    #[test]
+    fn test_round_trip() {
+        let fs = parse(&read_to_string("./src/d7/test.txt").unwrap()).unwrap();
+        let transcript = fs.transcript();
+        let reparsed = parse(&transcript).unwrap();
+
+        // Arenas are laid out in discovery order, compare the trees instead.
+        assert_eq!(reparsed.to_json(), fs.to_json());
+        assert_eq!(reparsed.transcript(), transcript);
+        let imported = FileSystem::from_json(&fs.to_json()).unwrap();
+        assert_eq!(imported.tree(), fs.tree());
+
+        let messy = parse("$ cd x\n$ ls\n1 a\ndir empty\n$ cd /\n$ ls\n2 b\n").unwrap();
+        let json = messy.to_json();
+        assert_eq!(
+            json,
+            r#"{
+  "name": "/",
+  "type": "dir",
+  "size": 3,
+  "children": [
+    {
+      "name": "x",
+      "type": "dir",
+      "size": 1,
+      "children": [
+        {
+          "name": "a",
+          "type": "file",
+          "size": 1
+        },
+        {
+          "name": "empty",
+          "type": "dir",
+          "size": 0,
+          "children": []
         }
+      ]
+    },
+    {
+      "name": "b",
+      "type": "file",
+      "size": 2
     }
-
-    (parent, chars.as_str())
+  ]
 }
+"#
+        );
+        let imported = FileSystem::from_json(&json).unwrap();
+        assert_eq!(parse(&imported.transcript()).unwrap().to_json(), json);
 
-fn get_line_type(line: &str) -> LineType {
-    let mut splitted = line.split_whitespace();
-    let first_word = splitted.next().unwrap();
-    let sec_word = splitted.next().unwrap();
+        // Whatever `parse` accepts survives the trip through JSON.
+        let odd = parse("$ cd /\n$ cd ..\n$ ls\ndir .a\n1 ...\n$ cd .a\n$ ls\n2 b.\n").unwrap();
+        let imported = FileSystem::from_json(&odd.to_json()).unwrap();
+        assert_eq!(imported.transcript(), odd.transcript());
+    }
 
-    match first_word {
-        "$" => match sec_word {
-            "ls" => LineType::Command(Command::Ls),
-            _ => {
-                let third_word = splitted.next().unwrap();
+    #[test]
+    fn test_bad_json() {
+        let dir = |children: &str| {
+            format!(r#"{{"name": "/", "type": "dir", "size": 1, "children": [{children}]}}"#)
+        };
+        let file = r#"{"name": "f", "type": "file", "size": 1}"#;
 
-                LineType::Command(Command::Cd(third_word))
-            }
-        },
-        "dir" => LineType::InfoDir(sec_word),
-        _ => LineType::InfoFile((u32::from_str_radix(first_word, 10).unwrap(), sec_word)),
+        assert!(FileSystem::from_json(&dir(file)).is_ok());
+        assert_eq!(
+            FileSystem::from_json(&dir("")).unwrap_err(),
+            "/: size 1 but its contents add up to 0"
+        );
+        assert!(FileSystem::from_json(&dir(&format!("{file}, {file}"))).is_err());
+        assert!(
+            FileSystem::from_json(&dir(&file.replace(r#""f""#, r#""a b""#)))
+                .unwrap_err()
+                .contains("invalid name")
+        );
+        assert!(FileSystem::from_json(&dir(&file.replace("file", "link"))).is_err());
+        assert!(FileSystem::from_json(&dir(file).replace(']', "")).is_err());
+        assert!(FileSystem::from_json(&(dir(file) + "}")).is_err());
+        assert!(FileSystem::from_json(r#"{"name": "/", "type": "file", "size": 0}"#).is_err());
+
+        let escaped = dir(r#"{"name": "\u00e9\"", "type": "file", "size": 1}"#);
+        let fs = FileSystem::from_json(&escaped).unwrap();
+        assert!(fs.lookup("/é\"").is_some());
+        assert_eq!(FileSystem::from_json(&fs.to_json()).unwrap(), fs);
+        assert!(FileSystem::from_json(&dir(file).replace(r#""f""#, r#""\x""#)).is_err());
     }
-}
-pub fn do_first_part(fpath: &str) -> u32 {
-    let file = read_to_string(fpath).unwrap();
-    let mut root = FsNode::new();
-    let _ = parse(&file, &mut root);
-    root.calc_size();
 
-    root.children.unwrap()[0].get_dir_sum_size_le_than(100000)
-}
+    #[test]
+    fn test_materialise() {
+        let target = std::env::temp_dir().join(format!("d7_test_{}", std::process::id()));
+        let fs = parse(&read_to_string("./src/d7/test.txt").unwrap()).unwrap();
 
-pub fn do_sec_part(fpath: &str) -> u32 {
-    let file = read_to_string(fpath).unwrap();
-    let mut root = FsNode::new();
-    let _ = parse(&file, &mut root);
-    root.calc_size();
+        fs.materialise(&target.join("sparse"), FileContents::Sparse)
+            .unwrap();
+        let scanned = scan(&target.join("sparse")).unwrap();
+        // Scanning sorts entries by name, compare the sorted reports.
+        assert_eq!(scanned.du(), fs.du());
+        assert_eq!(
+            scanned.largest_files(usize::MAX),
+            fs.largest_files(usize::MAX)
+        );
+        let transcript = scan_transcript(&target.join("sparse")).unwrap();
+        assert_eq!(parse(&transcript).unwrap().to_json(), scanned.to_json());
 
-    let free = 70_000_000 - root.size;
-    let need_to_free = 30_000_000 - free;
+        fs.materialise(
+            &target.join("zeros"),
+            FileContents::ZeroFilled { cap: 10_000 },
+        )
+        .unwrap();
+        let read = std::fs::read(target.join("zeros/a/e/i")).unwrap();
+        assert_eq!(read, vec![0; 584]);
+        let capped = scan(&target.join("zeros")).unwrap();
+        assert_eq!(capped.size(capped.lookup("/d/k").unwrap()), 10_000);
 
-    root.children.unwrap()[0].get_closest_dir_size_greater_than(need_to_free, u32::MAX)
-}
+        std::fs::create_dir(target.join("escape")).unwrap();
+        std::fs::write(target.join("escape/bad name"), "").unwrap();
+        assert!(scan(&target.join("escape")).is_err());
+        assert!(scan(&target.join("missing")).is_err());
 
-#[cfg(test)]
-mod d7_test {
-    use super::*;
+        std::fs::remove_dir_all(&target).unwrap();
+    }
 
     #[test]
-    fn test_first() {
-        let res = do_first_part("./src/d7/test.txt");
+    fn test_query() {
+        let fs = parse(&read_to_string("./src/d7/test.txt").unwrap()).unwrap();
 
-        assert_eq!(res, 95437);
+        assert_eq!(fs.query().count(), 14);
+        assert_eq!(fs.query().glob("/").paths(), vec!["/"]);
+        assert_eq!(fs.query().glob("/*").count(), 4);
+        assert_eq!(
+            fs.query().glob("/**/*.*").files().paths(),
+            vec!["/a/h.lst", "/b.txt", "/c.dat", "/d/d.log", "/d/d.ext"]
+        );
+        assert_eq!(
+            fs.query().glob("/a/**/?").paths(),
+            vec!["/a/e", "/a/e/i", "/a/f", "/a/g"]
+        );
+        assert_eq!(fs.query().glob("/a/**").count(), 6);
+        assert_eq!(fs.query().glob("/**/e/*").paths(), vec!["/a/e/i"]);
+        assert_eq!(fs.query().glob("d*").paths(), vec!["/d"]);
+        assert_eq!(fs.query().glob("/x/**").count(), 0);
+
+        assert_eq!(
+            fs.query().files().depth(2..).sum(),
+            Some(584 + 29116 + 2557 + 62596 + 24933642)
+        );
+        assert_eq!(fs.query().files().depth(1..=1).max(), Some(14848514));
+        assert_eq!(fs.query().dirs().size(..1000).paths(), vec!["/a/e"]);
+        assert_eq!(fs.query().files().size(10_000_000..).count(), 1);
+        assert_eq!(fs.query().glob("/nothing").min(), None);
+        assert_eq!(fs.query().glob("/nothing").sum(), Some(0));
+
+        // Nested directories count the same file twice.
+        let big = parse("$ ls\ndir a\n$ cd a\n$ ls\n10000000000000000000 f\n").unwrap();
+        assert_eq!(big.query().files().sum(), Some(10_000_000_000_000_000_000));
+        assert_eq!(
+            big.query().dirs().glob("/a").sum(),
+            Some(10_000_000_000_000_000_000)
+        );
+        assert_eq!(big.query().dirs().sum(), None);
+        assert_eq!(big.query().dirs().max(), Some(10_000_000_000_000_000_000));
     }
 
     #[test]
-    fn test_sec() {
-        let res = do_sec_part("./src/d7/test.txt");
+    fn test_cleanup_budget() {
+        // Many similar files in one directory with an odd target no sum of
+        // even sizes can hit exactly, the worst case for the search.
+        let mut rng = TestRng::new(11);
+        let listing = (0..200)
+            .map(|idx| format!("{} f{idx}\n", 2 * (1_000_000 + rng.below(1_000_000))))
+            .collect::<String>();
+        let fs = parse(&format!("$ ls\n{listing}")).unwrap();
+        let disk = Disk {
+            capacity: fs.size(FileSystem::ROOT),
+            required: (fs.size(FileSystem::ROOT) / 3) | 1,
+        };
 
-        assert_eq!(res, 24933642);
+        let plan = fs.plan_cleanup(&disk, Deletable::Files).unwrap();
+        assert!(!plan.exact);
+        assert!(plan.freed >= plan.to_free);
+        assert_eq!(plan.deleted.iter().map(|d| d.0).sum::<u64>(), plan.freed);
     }
 }
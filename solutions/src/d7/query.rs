@@ -0,0 +1,138 @@
+use std::ops::{Bound, RangeBounds};
+
+use super::{FileSystem, NodeId};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Dir,
+    File,
+}
+
+type Range<T> = (Bound<T>, Bound<T>);
+
+fn to_range<T: Copy>(range: impl RangeBounds<T>) -> Range<T> {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+// Selection of nodes built up by chaining filters, then consumed by one of
+// the aggregations. Sizes are file sizes or directory totals.
+#[derive(Clone, Debug)]
+pub struct Query<'a> {
+    fs: &'a FileSystem,
+    glob: Option<Vec<String>>,
+    kind: Option<Kind>,
+    size: Range<u64>,
+    depth: Range<usize>,
+}
+
+impl FileSystem {
+    // Query matching every node, root included.
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            fs: self,
+            glob: None,
+            kind: None,
+            size: to_range(..),
+            depth: to_range(..),
+        }
+    }
+}
+
+impl<'a> Query<'a> {
+    // Absolute path pattern, `*` and `?` match within a name, a `**`
+    // component matches any number of directories.
+    pub fn glob(mut self, pattern: &str) -> Self {
+        let parts = pattern.split('/').filter(|p| !p.is_empty());
+        self.glob = Some(parts.map(str::to_string).collect());
+        self
+    }
+    pub fn dirs(mut self) -> Self {
+        self.kind = Some(Kind::Dir);
+        self
+    }
+    pub fn files(mut self) -> Self {
+        self.kind = Some(Kind::File);
+        self
+    }
+    pub fn size(mut self, range: impl RangeBounds<u64>) -> Self {
+        self.size = to_range(range);
+        self
+    }
+    // Depth counted from the root, which is at depth 0.
+    pub fn depth(mut self, range: impl RangeBounds<usize>) -> Self {
+        self.depth = to_range(range);
+        self
+    }
+
+    fn matches(&self, id: NodeId) -> bool {
+        let node = self.fs.node(id);
+        let kind = if node.is_dir() { Kind::Dir } else { Kind::File };
+        if self.kind.is_some_and(|k| k != kind)
+            || !self.size.contains(&node.size)
+            || !self.depth.contains(&self.fs.depth(id))
+        {
+            return false;
+        }
+        self.glob.as_ref().is_none_or(|glob| {
+            let path = self.fs.path(id);
+            let parts = path
+                .split('/')
+                .filter(|p| !p.is_empty())
+                .collect::<Vec<_>>();
+            match_parts(glob, &parts)
+        })
+    }
+
+    pub fn nodes(self) -> impl Iterator<Item = NodeId> + 'a {
+        let fs = self.fs;
+        fs.walk(FileSystem::ROOT)
+            .filter(move |id| self.matches(*id))
+    }
+    pub fn paths(self) -> Vec<String> {
+        let fs = self.fs;
+        self.nodes().map(|id| fs.path(id)).collect()
+    }
+    fn sizes(self) -> impl Iterator<Item = u64> + 'a {
+        let fs = self.fs;
+        self.nodes().map(|id| fs.size(id))
+    }
+    pub fn count(self) -> usize {
+        self.nodes().count()
+    }
+    // Total of the matched sizes, nested matches are counted each time so
+    // the total can exceed the tree size, `None` when it overflows.
+    pub fn sum(self) -> Option<u64> {
+        self.sizes()
+            .try_fold(0u64, |acc, size| acc.checked_add(size))
+    }
+    pub fn max(self) -> Option<u64> {
+        self.sizes().max()
+    }
+    pub fn min(self) -> Option<u64> {
+        self.sizes().min()
+    }
+}
+
+fn match_parts(glob: &[String], parts: &[&str]) -> bool {
+    match glob.split_first() {
+        None => parts.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=parts.len()).any(|skip| match_parts(rest, &parts[skip..]))
+        }
+        Some((first, rest)) => parts.split_first().is_some_and(|(part, others)| {
+            let pattern = first.chars().collect::<Vec<_>>();
+            let name = part.chars().collect::<Vec<_>>();
+            match_name(&pattern, &name) && match_parts(rest, others)
+        }),
+    }
+}
+
+fn match_name(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => (0..=name.len()).any(|skip| match_name(rest, &name[skip..])),
+        (Some(('?', rest)), Some((_, others))) => match_name(rest, others),
+        (Some((p, rest)), Some((c, others))) => p == c && match_name(rest, others),
+        (Some(_), None) => false,
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt::Write;
+
+use super::{FileSystem, FsNodeType, NodeId};
+
+impl FileSystem {
+    // Indented view of the whole tree in the format used by the puzzle text,
+    // directories listed with their total size.
+    pub fn tree(&self) -> String {
+        let mut out = String::new();
+        for id in self.walk(Self::ROOT) {
+            let node = self.node(id);
+            let kind = match node.node_type {
+                FsNodeType::File(_) => "file",
+                FsNodeType::Root | FsNodeType::Dir(_) => "dir",
+            };
+            let indent = "  ".repeat(self.depth(id));
+            writeln!(
+                out,
+                "{indent}- {} ({kind}, size={})",
+                node.name(),
+                node.size
+            )
+            .unwrap();
+        }
+
+        out
+    }
+
+    // Every directory with its total size, largest first.
+    pub fn du(&self) -> Vec<(u64, String)> {
+        self.sorted_by_size(self.dirs())
+    }
+    pub fn largest_files(&self, n: usize) -> Vec<(u64, String)> {
+        let mut files = self.sorted_by_size(self.files());
+        files.truncate(n);
+
+        files
+    }
+    fn sorted_by_size(&self, ids: impl Iterator<Item = NodeId>) -> Vec<(u64, String)> {
+        let mut res = ids
+            .map(|id| (self.size(id), self.path(id)))
+            .collect::<Vec<_>>();
+        res.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        res
+    }
+}
+
+// Formats `du` style entries as `size<TAB>path` lines.
+pub fn format_listing(entries: &[(u64, String)]) -> String {
+    entries
+        .iter()
+        .map(|(size, path)| format!("{size}\t{path}\n"))
+        .collect()
+}
@@ -0,0 +1,31 @@
+use std::fmt::Write;
+
+use super::{FileSystem, FsNodeType, NodeId};
+
+impl FileSystem {
+    // Canonical transcript that `parse` turns back into this tree: every
+    // directory is entered, listed and left again in walk order.
+    pub fn transcript(&self) -> String {
+        let mut out = String::from("$ cd /\n");
+        self.write_listing(Self::ROOT, &mut out);
+
+        out
+    }
+    fn write_listing(&self, dir: NodeId, out: &mut String) {
+        out.push_str("$ ls\n");
+        for child in self.list(dir) {
+            let node = self.node(*child);
+            match &node.node_type {
+                FsNodeType::File(name) => writeln!(out, "{} {name}", node.size).unwrap(),
+                _ => writeln!(out, "dir {}", node.name()).unwrap(),
+            }
+        }
+        for child in self.list(dir) {
+            if let FsNodeType::Dir(name) = &self.node(*child).node_type {
+                writeln!(out, "$ cd {name}").unwrap();
+                self.write_listing(*child, out);
+                out.push_str("$ cd ..\n");
+            }
+        }
+    }
+}
@@ -2,33 +2,44 @@ use std::fs::read_to_string;
 
 pub fn do_first_part(fpath: &str) -> u32 {
     let file = read_to_string(fpath).unwrap();
-    let matrix = parse(&file);
-
-    let side_size = matrix.len();
-    let mut vis_matrix = vec![vec![false; side_size]; side_size];
-    for row_step in 0..side_size {
-        let (mut l_t_r, mut r_t_l, mut t_t_d, mut d_t_t) = (None, None, None, None);
-        for col_step in 0..side_size {
-            let h_l_r = matrix[row_step][col_step];
-            let h_r_l = matrix[row_step][side_size - col_step - 1];
-            let h_t_d = matrix[col_step][row_step];
-            let h_d_t = matrix[side_size - col_step - 1][row_step];
+    let matrix = parse(&file).unwrap();
+
+    count_visible(&matrix)
+}
+
+// Both computations expect a rectangular grid as checked by `parse`.
+fn count_visible(matrix: &[Vec<u32>]) -> u32 {
+    let (height, width) = (matrix.len(), matrix.first().map_or(0, Vec::len));
+    let mut vis_matrix = vec![vec![false; width]; height];
+    for row in 0..height {
+        let (mut l_t_r, mut r_t_l) = (None, None);
+        for col in 0..width {
+            let h_l_r = matrix[row][col];
+            let h_r_l = matrix[row][width - col - 1];
 
             if l_t_r.is_none() || l_t_r.unwrap() < h_l_r {
                 l_t_r = Some(h_l_r);
-                vis_matrix[row_step][col_step] |= true;
+                vis_matrix[row][col] |= true;
             }
             if r_t_l.is_none() || r_t_l.unwrap() < h_r_l {
                 r_t_l = Some(h_r_l);
-                vis_matrix[row_step][side_size - col_step - 1] |= true;
+                vis_matrix[row][width - col - 1] |= true;
             }
+        }
+    }
+    for col in 0..width {
+        let (mut t_t_d, mut d_t_t) = (None, None);
+        for row in 0..height {
+            let h_t_d = matrix[row][col];
+            let h_d_t = matrix[height - row - 1][col];
+
             if t_t_d.is_none() || t_t_d.unwrap() < h_t_d {
                 t_t_d = Some(h_t_d);
-                vis_matrix[col_step][row_step] |= true;
+                vis_matrix[row][col] |= true;
             }
             if d_t_t.is_none() || d_t_t.unwrap() < h_d_t {
                 d_t_t = Some(h_d_t);
-                vis_matrix[side_size - col_step - 1][row_step] |= true;
+                vis_matrix[height - row - 1][col] |= true;
             }
         }
     }
@@ -54,23 +65,25 @@ impl HeightWithIdx {
 
 pub fn do_sec_part(fpath: &str) -> u32 {
     let file = read_to_string(fpath).unwrap();
-    let matrix = parse(&file);
+    let matrix = parse(&file).unwrap();
+
+    best_scenic_score(&matrix)
+}
 
-    let side_size = matrix.len();
-    let mut vis_matrix = vec![vec![1u32; side_size]; side_size];
+fn best_scenic_score(matrix: &[Vec<u32>]) -> u32 {
+    let (height, width) = (matrix.len(), matrix.first().map_or(0, Vec::len));
+    let mut vis_matrix = vec![vec![1u32; width]; height];
 
-    let mut ltr_help: Vec<HeightWithIdx> = Vec::with_capacity(side_size);
-    let mut rtl_help: Vec<HeightWithIdx> = Vec::with_capacity(side_size);
-    let mut ttd_help: Vec<HeightWithIdx> = Vec::with_capacity(side_size);
-    let mut dtt_help: Vec<HeightWithIdx> = Vec::with_capacity(side_size);
-    for row in 0..side_size {
+    let mut ltr_help: Vec<HeightWithIdx> = Vec::with_capacity(width);
+    let mut rtl_help: Vec<HeightWithIdx> = Vec::with_capacity(width);
+    let mut ttd_help: Vec<HeightWithIdx> = Vec::with_capacity(height);
+    let mut dtt_help: Vec<HeightWithIdx> = Vec::with_capacity(height);
+    for row in 0..height {
         ltr_help.clear();
         rtl_help.clear();
-        ttd_help.clear();
-        dtt_help.clear();
-        for col in 0..side_size {
+        for col in 0..width {
             fill_vision(
-                &matrix,
+                matrix,
                 row,
                 col,
                 &mut vis_matrix,
@@ -81,20 +94,25 @@ pub fn do_sec_part(fpath: &str) -> u32 {
             );
 
             fill_vision(
-                &matrix,
+                matrix,
                 row,
-                side_size - col - 1,
+                width - col - 1,
                 &mut vis_matrix,
                 &mut rtl_help,
                 |cur, prev| prev - cur,
                 |cur, size| size - cur - 1,
                 |_, col| col,
             );
-
+        }
+    }
+    for col in 0..width {
+        ttd_help.clear();
+        dtt_help.clear();
+        for row in 0..height {
             fill_vision(
-                &matrix,
-                col,
+                matrix,
                 row,
+                col,
                 &mut vis_matrix,
                 &mut ttd_help,
                 |cur, prev| cur - prev,
@@ -103,9 +121,9 @@ pub fn do_sec_part(fpath: &str) -> u32 {
             );
 
             fill_vision(
-                &matrix,
-                side_size - col - 1,
-                row,
+                matrix,
+                height - row - 1,
+                col,
                 &mut vis_matrix,
                 &mut dtt_help,
                 |cur, prev| prev - cur,
@@ -115,15 +133,16 @@ pub fn do_sec_part(fpath: &str) -> u32 {
         }
     }
 
-    *vis_matrix
+    vis_matrix
         .iter()
-        .map(|row| row.iter().max().unwrap())
+        .filter_map(|row| row.iter().max())
         .max()
-        .unwrap()
+        .copied()
+        .unwrap_or(0)
 }
 
 fn fill_vision(
-    heights: &Vec<Vec<u32>>,
+    heights: &[Vec<u32>],
     row: usize,
     col: usize,
     vis_matrix: &mut Vec<Vec<u32>>,
@@ -133,7 +152,9 @@ fn fill_vision(
     corn_dist_calc: fn(usize, usize) -> usize,
     act_idx_extractor: fn(usize, usize) -> usize,
 ) {
-    let size = heights.len();
+    // The extractor picks the row or the column index, applied to the grid
+    // dimensions it gives the length of the line being walked.
+    let size = act_idx_extractor(heights.len(), heights[0].len());
     let cur_el_h = heights[row][col];
     let act_idx = act_idx_extractor(row, col);
     if corn_dist_calc(act_idx, size) == 0 {
@@ -156,25 +177,38 @@ fn fill_vision(
     }
 }
 
-fn parse(file: &str) -> Vec<Vec<u32>> {
-    let mut res = Vec::new();
-    for line in file.split('\n') {
+// Rows of digit heights, all rows must have the same length.
+pub fn parse(file: &str) -> Result<Vec<Vec<u32>>, String> {
+    let mut res: Vec<Vec<u32>> = Vec::new();
+    for (idx, line) in file.lines().enumerate() {
         if line.is_empty() {
             continue;
         }
         let mut v = Vec::new();
         for ch in line.chars() {
-            v.push(ch.to_digit(10).unwrap());
+            let height = ch
+                .to_digit(10)
+                .ok_or_else(|| format!("Line {}: {ch:?} is not a tree height", idx + 1))?;
+            v.push(height);
+        }
+        if let Some(first) = res.first().filter(|first| first.len() != v.len()) {
+            return Err(format!(
+                "Line {}: row has {} trees, expected {}",
+                idx + 1,
+                v.len(),
+                first.len()
+            ));
         }
         res.push(v);
     }
 
-    res
+    Ok(res)
 }
 
 #[cfg(test)]
 mod d8_test {
     use super::*;
+    use crate::test_rng::TestRng;
 
     #[test]
     fn test_first() {
@@ -189,4 +223,68 @@ mod d8_test {
 
         assert_eq!(res, 8);
     }
+
+    // Straightforward per-tree look in all four directions.
+    fn brute_force(matrix: &[Vec<u32>]) -> (u32, u32) {
+        let (height, width) = (matrix.len() as isize, matrix[0].len() as isize);
+        let (mut visible, mut best) = (0, 0);
+        for row in 0..height {
+            for col in 0..width {
+                let h = matrix[row as usize][col as usize];
+                let (mut seen, mut score) = (false, 1);
+                for (dr, dc) in [(0, 1), (0, -1), (1, 0), (-1, 0)] {
+                    let (mut r, mut c, mut dist) = (row + dr, col + dc, 0);
+                    let mut blocked = false;
+                    while (0..height).contains(&r) && (0..width).contains(&c) {
+                        dist += 1;
+                        if matrix[r as usize][c as usize] >= h {
+                            blocked = true;
+                            break;
+                        }
+                        r += dr;
+                        c += dc;
+                    }
+                    seen |= !blocked;
+                    score *= dist;
+                }
+                visible += u32::from(seen);
+                best = best.max(score);
+            }
+        }
+
+        (visible, best)
+    }
+
+    #[test]
+    fn test_rectangular() {
+        let mut rng = TestRng::new(7);
+        for (height, width) in [(1, 1), (1, 6), (6, 1), (3, 8), (8, 3), (5, 12), (13, 4)] {
+            let matrix = (0..height)
+                .map(|_| (0..width).map(|_| rng.below(10) as u32).collect())
+                .collect::<Vec<Vec<u32>>>();
+
+            let expected = brute_force(&matrix);
+            assert_eq!(count_visible(&matrix), expected.0, "{height}x{width}");
+            assert_eq!(best_scenic_score(&matrix), expected.1, "{height}x{width}");
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("123\n456\n").unwrap(),
+            vec![vec![1, 2, 3], vec![4, 5, 6]]
+        );
+        assert_eq!(
+            parse("123\n45\n").unwrap_err(),
+            "Line 2: row has 2 trees, expected 3"
+        );
+        assert_eq!(
+            parse("12\n4x\n").unwrap_err(),
+            "Line 2: 'x' is not a tree height"
+        );
+        assert_eq!(parse("").unwrap(), Vec::<Vec<u32>>::new());
+        assert_eq!(count_visible(&[]), 0);
+        assert_eq!(best_scenic_score(&[]), 0);
+    }
 }
@@ -10,3 +10,6 @@ pub mod d6;
 pub mod d7;
 pub mod d8;
 pub mod d9;
+
+#[cfg(test)]
+mod test_rng;